@@ -0,0 +1,131 @@
+// Synthetic fixture for tracking directory listing latency.
+// Run with `cargo bench --bench listing`; CDA_BENCH_SIZE scales the tree.
+
+#[allow(dead_code)]
+#[path = "../src/visibility.rs"]
+mod visibility;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+use visibility::Visibility;
+
+const RUNS: usize = 5;
+const DEPTH: usize = 200;
+
+struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    fn create(size: usize) -> io::Result<Self> {
+        let root = env::temp_dir().join(format!("cda-bench-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let wide = root.join("wide");
+        fs::create_dir_all(&wide)?;
+        for i in 0..size {
+            fs::create_dir(wide.join(format!("dir{:06}", i)))?;
+            fs::write(wide.join(format!("file{:06}", i)), "")?;
+        }
+        for i in 0..size / 10 {
+            symlink(
+                wide.join(format!("dir{:06}", i)),
+                wide.join(format!("link{:06}", i)),
+            )?;
+        }
+
+        let mut deep = root.join("deep");
+        for _ in 0..DEPTH {
+            deep = deep.join("d");
+        }
+        fs::create_dir_all(&deep)?;
+
+        Ok(Self { root })
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn bench(name: &str, mut f: impl FnMut() -> usize) {
+    let mut times = vec![];
+    let mut count = 0;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        count = f();
+        times.push(started.elapsed());
+    }
+    times.sort();
+    let median = times[RUNS / 2];
+    let min = times[0];
+    println!(
+        "{:<24} {:>8} dirs  median {:>8.2?}  min {:>8.2?}",
+        name, count, median, min
+    );
+}
+
+fn list_deep(visibility: &Visibility, dir: &Path) -> usize {
+    let mut count = 0;
+    let mut next = Some(dir.to_path_buf());
+    while let Some(dir) = next.take() {
+        next = visibility
+            .subdirs(&dir)
+            .expect("fixture is readable")
+            .into_iter()
+            .next()
+            .map(|(p, _)| p);
+        count += 1;
+    }
+    count
+}
+
+fn main() -> io::Result<()> {
+    let size = env::var("CDA_BENCH_SIZE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(50_000);
+
+    let started = Instant::now();
+    let fixture = Fixture::create(size)?;
+    println!(
+        "fixture: {} dirs, {} files, {} symlinks ({:.2?})",
+        size,
+        size,
+        size / 10,
+        started.elapsed()
+    );
+
+    let plain = Visibility::new(true, false, &[]).expect("no globs");
+    let filtered = Visibility::new(false, true, &["*7".to_string()]).expect("glob is valid");
+    let wide = fixture.root.join("wide");
+    let deep = fixture.root.join("deep");
+
+    bench("wide", || {
+        plain.subdirs(&wide).expect("fixture is readable").len()
+    });
+    bench("wide (ignore rules)", || {
+        filtered.subdirs(&wide).expect("fixture is readable").len()
+    });
+    bench("wide (first batch)", || {
+        let mut count = 0;
+        plain
+            .for_each_subdir(&wide, |_, _| {
+                count += 1;
+                count < 256
+            })
+            .expect("fixture is readable");
+        count
+    });
+    bench("deep", || list_deep(&plain, &deep));
+
+    Ok(())
+}
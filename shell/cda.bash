@@ -1,8 +1,20 @@
-_exec_cda() {
-  READLINE_LINE="$(cda)"
+__cda_widget() {
+  local cmd
+  cmd="$(command cda --shell bash)" || return
+  READLINE_LINE="$cmd"
+  READLINE_POINT=${#READLINE_LINE}
+}
+
+__cda_exec_widget() {
+  READLINE_LINE="$(command cda --shell bash)"
   READLINE_POINT=${#READLINE_LINE}
 }
 
 CDA_BINDKEY="${CDA_BINDKEY:-\\ef}"
 
-bind -x "\"$CDA_BINDKEY\":_exec_cda"
+if [ -n "$CDA_EXEC" ]; then
+  bind -x '"\C-x\C-a\C-d": __cda_exec_widget'
+  bind "\"$CDA_BINDKEY\": \" \C-b\C-k \C-u\C-x\C-a\C-d\C-m\C-y\C-h\e \C-y\ey\C-x\C-x\C-d\""
+else
+  bind -x "\"$CDA_BINDKEY\":__cda_widget"
+fi
@@ -0,0 +1,16 @@
+function __cda_widget
+    set -l cmd (command cda --shell fish)
+    if test -n "$cmd"
+        if set -q CDA_EXEC
+            eval $cmd
+        else
+            commandline --replace -- $cmd
+            commandline -f end-of-line
+        end
+    end
+    commandline -f repaint
+end
+
+set -q CDA_BINDKEY; or set -g CDA_BINDKEY \ef
+bind $CDA_BINDKEY __cda_widget
+bind -M insert $CDA_BINDKEY __cda_widget
@@ -1,4 +1,8 @@
+pub mod bookmarks;
 pub mod core;
+pub mod filter;
+pub mod finder;
 pub mod popup;
+pub mod prompt;
 
-pub use core::App;
+pub use core::{App, Exit};
@@ -0,0 +1,92 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::Style,
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
+
+use crate::bookmarks::Bookmark;
+use crate::config::Theme;
+use crate::keymap::KeyChord;
+
+pub struct BookmarkPicker {
+    pub visible: bool,
+    bookmarks: Vec<Bookmark>,
+    selected: usize,
+}
+
+impl BookmarkPicker {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            bookmarks: vec![],
+            selected: 0,
+        }
+    }
+
+    pub fn open(&mut self, bookmarks: Vec<Bookmark>) {
+        self.visible = true;
+        self.bookmarks = bookmarks;
+        self.selected = 0;
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<Bookmark>) {
+        self.bookmarks = bookmarks;
+        self.selected = self.selected.min(self.bookmarks.len().saturating_sub(1));
+    }
+
+    pub fn hide(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn move_selection(&mut self, direction: isize) {
+        if self.bookmarks.is_empty() {
+            return;
+        }
+        self.selected = (self.selected as isize + direction)
+            .clamp(0, self.bookmarks.len() as isize - 1) as usize;
+    }
+
+    pub fn selected(&self) -> Option<&Bookmark> {
+        self.bookmarks.get(self.selected)
+    }
+
+    pub fn draw(&self, f: &mut Frame, popup_area: Rect, add_key: Option<KeyChord>, theme: &Theme) {
+        f.render_widget(Clear, popup_area);
+
+        let block = Block::default()
+            .title("bookmarks")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL);
+
+        let view_height = popup_area.height.saturating_sub(2) as usize;
+        let offset = (self.selected + 1).saturating_sub(view_height);
+        let items: Vec<ListItem> = if self.bookmarks.is_empty() {
+            let hint = match add_key {
+                Some(key) => format!("no bookmarks ({} to add)", key),
+                None => "no bookmarks".to_string(),
+            };
+            vec![ListItem::new(hint)]
+        } else {
+            self.bookmarks
+                .iter()
+                .enumerate()
+                .skip(offset)
+                .take(view_height)
+                .map(|(i, b)| {
+                    let mut style = if b.path.is_dir() {
+                        Style::default().fg(theme.symlink)
+                    } else {
+                        Style::default().fg(theme.broken)
+                    };
+                    if i == self.selected {
+                        style = style.bg(theme.focus_bg);
+                    }
+                    ListItem::new(format!("{} -> {}", b.name, b.path.display())).style(style)
+                })
+                .collect()
+        };
+
+        f.render_widget(List::new(items).block(block), popup_area);
+    }
+}
@@ -1,222 +1,478 @@
-use std::io::Stderr;
-use std::time::Instant;
-use std::{env, fs, io, path::Path};
-
-use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
-    execute,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
-};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    Terminal,
-    backend::CrosstermBackend,
-    style::{Color, Style},
+    style::{Modifier, Style},
+    text::Line,
     widgets::{Block, Borders, List, ListItem},
 };
 
+use crate::app::bookmarks::BookmarkPicker;
+use crate::app::filter::Filter;
+use crate::app::finder::Finder;
 use crate::app::popup::Popup;
-use crate::tree::{DirEntry, LinkStatus};
+use crate::app::prompt::Prompt;
+use crate::config::Config;
+use crate::keymap::Action;
+use crate::loader::{LoadUpdate, Loader};
+use crate::sort::Sorter;
+use crate::terminal::{self, Height, Signal, SignalWatch, Tui};
+use crate::tree::{DirEntry, LinkStatus, NodeId, NodeKind, Tree};
+use crate::visibility::Visibility;
+use crate::watcher::Watcher;
+use crate::{bookmarks, history};
+
+pub enum Exit {
+    Select(PathBuf),
+    Cancel,
+    Interrupt,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Filesystem,
+    List { stat: bool },
+}
 
 pub struct App {
-    root: DirEntry,
-    focus_path: Vec<usize>,
+    config: Config,
+    visibility: Visibility,
+    sorter: Sorter,
+    source: Source,
+    watcher: Option<Watcher>,
+    loader: Loader,
+    tree: Tree,
+    bookmarks_root: NodeId,
+    fs_root: NodeId,
+    focus: NodeId,
     scroll_offset: usize,
     popup: Popup,
+    filter: Filter,
+    finder: Finder,
+    prompt: Prompt,
+    bookmark_picker: BookmarkPicker,
+    bookmark_target: Option<PathBuf>,
+    reveal_target: Option<PathBuf>,
     search_buffer: String,
     last_input_time: Option<Instant>,
+    status: Option<String>,
 }
 
 impl App {
-    pub fn new() -> io::Result<Self> {
-        let current = env::current_dir()?;
-        let mut ancestors: Vec<_> = vec![];
-        let mut p = current.as_path();
-        while p != Path::new("/") {
-            ancestors.push(p.to_path_buf());
-            if let Some(parent) = p.parent() {
-                p = parent;
-            } else {
-                break;
+    pub fn new(
+        start: &Path,
+        top: &Path,
+        candidates: Vec<PathBuf>,
+        config: Config,
+    ) -> io::Result<Self> {
+        let mut visibility = config.behavior.visibility();
+        visibility.pin(start);
+        let history = history::ranked().unwrap_or_default();
+        let sorter = Sorter::new(config.behavior.sort_mode(), &history);
+
+        let load_timeout = Some(config.behavior.load_timeout_ms)
+            .filter(|&ms| ms > 0)
+            .map(Duration::from_millis);
+        let watcher = if config.behavior.watch {
+            Watcher::new().ok()
+        } else {
+            None
+        };
+
+        let mut ancestors: Vec<_> = start
+            .ancestors()
+            .take_while(|p| p.starts_with(top))
+            .map(Path::to_path_buf)
+            .collect();
+        ancestors.reverse();
+
+        let mut tree = Tree::new();
+        let root = tree.root();
+        let mut first_candidate = None;
+        if !candidates.is_empty() {
+            let mut input = DirEntry::new_virtual("Input");
+            input.expanded = true;
+            let input = tree.add(root, input);
+            for path in candidates {
+                let mut entry = DirEntry::new(path);
+                entry.name = entry.path.display().to_string();
+                first_candidate.get_or_insert(tree.add(input, entry));
             }
         }
-        ancestors.push("/".into());
-        ancestors.reverse();
+        let recent = tree.add(root, DirEntry::new_virtual("Recent"));
+        for e in history
+            .into_iter()
+            .filter(|e| e.path.is_dir())
+            .take(config.behavior.recent_limit)
+        {
+            let mut entry = DirEntry::new(e.path);
+            entry.name = entry.path.display().to_string();
+            tree.add(recent, entry);
+        }
+        let bookmarks_root = tree.add(root, DirEntry::new_virtual("Bookmarks"));
+        tree.set_children(bookmarks_root, bookmark_entries());
 
-        let mut root = DirEntry::new("/".into());
-        root.expanded = true;
+        let mut top_entry = DirEntry::new(top.to_path_buf());
+        top_entry.name = top.display().to_string();
+        top_entry.expanded = true;
+        let fs_root = tree.add(root, top_entry);
 
-        let mut node = &mut root;
-        let mut focus_path = vec![];
+        let mut focus = fs_root;
+        let mut status = None;
         for (depth, path) in ancestors.iter().skip(1).enumerate() {
-            if depth == ancestors.len() - 2 {
-                node.load_children();
-                if let Some(i) = node
-                    .children
-                    .iter_mut()
-                    .position(|c| path.starts_with(&c.path))
-                {
-                    node.children[i].expanded = true;
-                    focus_path.push(i);
-                    node = &mut node.children[i];
+            let child = if depth == ancestors.len() - 2 {
+                tree.load_children(focus, &visibility, &sorter);
+                if tree[focus].error.is_some() {
+                    tree.load_only(focus, path)
+                } else {
+                    tree[focus]
+                        .children
+                        .iter()
+                        .copied()
+                        .find(|&c| path.starts_with(&tree[c].path))
+                }
+            } else {
+                tree.load_only(focus, path)
+            };
+            match child {
+                Some(child) => {
+                    tree[child].expanded = true;
+                    focus = child;
+                }
+                None => {
+                    status = Some(format!("cannot open {}", path.display()));
+                    break;
                 }
-            } else if let Some(i) = node.load_only(path) {
-                node.children[i].expanded = true;
-                focus_path.push(i);
-                node = &mut node.children[i];
             }
         }
 
-        {
-            let mut node = &mut root;
-            for &i in &focus_path {
-                node = &mut node.children[i];
+        let mut app = Self::with_tree(config, visibility, sorter, tree);
+        app.watcher = watcher;
+        app.loader = Loader::new(load_timeout);
+        app.bookmarks_root = bookmarks_root;
+        app.fs_root = fs_root;
+        app.focus = focus;
+        app.status = status;
+        app.load_async(focus);
+        if let Some(candidate) = first_candidate {
+            app.focus = candidate;
+        }
+        Ok(app)
+    }
+
+    pub fn from_list(paths: Vec<PathBuf>, stat: bool, config: Config) -> Self {
+        let visibility = config.behavior.visibility();
+        let sorter = if stat {
+            Sorter::new(config.behavior.sort_mode(), &[])
+        } else {
+            Sorter::without_stat(config.behavior.sort_mode())
+        };
+        let tree = Tree::from_paths(paths, stat, &sorter);
+
+        let root = tree.root();
+        let mut app = Self::with_tree(config, visibility, sorter, tree);
+        app.source = Source::List { stat };
+        for &top in &app.tree[root].children.clone() {
+            let mut node = top;
+            while !app.tree[node].children.is_empty() {
+                app.tree[node].expanded = true;
+                match app.tree[node].children[..] {
+                    [only] => node = only,
+                    _ => break,
+                }
             }
-            node.load_children();
         }
+        if let Some(&first) = app.tree[root].children.first() {
+            app.fs_root = first;
+            app.focus = first;
+        }
+        app
+    }
 
-        Ok(Self {
-            root,
-            focus_path,
+    fn with_tree(config: Config, visibility: Visibility, sorter: Sorter, tree: Tree) -> Self {
+        let root = tree.root();
+        Self {
+            config,
+            visibility,
+            sorter,
+            source: Source::Filesystem,
+            watcher: None,
+            loader: Loader::new(None),
+            tree,
+            bookmarks_root: root,
+            fs_root: root,
+            focus: root,
             scroll_offset: 0,
             popup: Popup::new(),
+            filter: Filter::new(),
+            finder: Finder::new(),
+            prompt: Prompt::new(),
+            bookmark_picker: BookmarkPicker::new(),
+            bookmark_target: None,
+            reveal_target: None,
             search_buffer: String::new(),
             last_input_time: None,
-        })
+            status: None,
+        }
     }
 
-    pub fn run(&mut self) -> io::Result<()> {
-        let mut terminal = self.init_terminal()?;
+    pub fn run(&mut self, height: Option<Height>) -> io::Result<Exit> {
+        let mut signals = SignalWatch::new()?;
+        let mut terminal = terminal::init(height)?;
+        let result = self.event_loop(&mut terminal, &mut signals);
+        terminal::restore();
+        result
+    }
 
+    fn event_loop(&mut self, terminal: &mut Tui, signals: &mut SignalWatch) -> io::Result<Exit> {
         loop {
-            let mut lines = vec![];
-            self.walk(&self.root, "".to_string(), &mut lines, vec![]);
+            for signal in signals.pending() {
+                match signal {
+                    Signal::Terminate => return Ok(Exit::Interrupt),
+                    Signal::Suspend => terminal::suspend(terminal)?,
+                    Signal::Continue => terminal::resume(terminal)?,
+                }
+            }
 
-            let height = terminal.size()?.height as usize;
+            self.finder.poll();
+            self.poll_loader();
+            self.poll_watcher();
+
+            let rows = self.rows();
+
+            let height = terminal.get_frame().area().height as usize;
 
             terminal.draw(|f| {
+                let theme = &self.config.theme;
                 let area = f.area();
-                let items: Vec<ListItem> = lines
+                let popup_area = self.config.behavior.popup_area(area);
+                let items: Vec<ListItem> = rows
                     .iter()
-                    .enumerate()
                     .skip(self.scroll_offset)
                     .take(area.height as usize)
-                    .map(|(_i, (text, node, path))| {
+                    .map(|&(id, depth)| {
+                        let node = &self.tree[id];
+                        let text = self.line_text(id, depth);
+                        let line = self
+                            .filter
+                            .highlight(&text, depth * 4 + 2, id, theme.matched);
                         let mut style = Style::default();
-                        if self.focus_path == *path {
-                            style = style.bg(Color::Rgb(40, 40, 40)).fg(Color::White);
+                        if self.focus == id {
+                            style = style.bg(theme.focus_bg).fg(theme.focus_fg);
                         }
 
                         style = match node.link_status {
-                            LinkStatus::SymlinkOk => style.fg(Color::Cyan),
-                            LinkStatus::SymlinkBroken => style.fg(Color::Red),
+                            LinkStatus::SymlinkOk => style.fg(theme.symlink),
+                            LinkStatus::SymlinkBroken | LinkStatus::SymlinkLoop => {
+                                style.fg(theme.broken)
+                            }
                             LinkStatus::Normal => style,
                         };
+                        if node.error.is_some() {
+                            style = style.fg(theme.error);
+                        }
+                        match node.kind {
+                            NodeKind::Virtual => {
+                                style = style.fg(theme.virtual_root).add_modifier(Modifier::BOLD)
+                            }
+                            NodeKind::Placeholder => {
+                                style = style.fg(theme.border).add_modifier(Modifier::ITALIC)
+                            }
+                            NodeKind::Dir => {}
+                        }
 
-                        ListItem::new(text.clone()).style(style)
+                        ListItem::new(line).style(style)
                     })
                     .collect();
 
-                let list = List::new(items).block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Gray)),
-                );
+                let mut block = Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border))
+                    .title_top(Line::from(format!(" sort: {} ", self.sorter.mode)).right_aligned());
+                if self.filter.active {
+                    block = block.title_bottom(self.filter.status_line(self.focus, theme));
+                } else if let Some(status) = &self.status {
+                    block = block.title_bottom(
+                        Line::from(format!(" {} ", status))
+                            .style(Style::default().fg(theme.broken)),
+                    );
+                } else if let Some(error) = &self.focused().error {
+                    let message = format!(" {}: {} ", self.focused().path.display(), error);
+                    block = block
+                        .title_bottom(Line::from(message).style(Style::default().fg(theme.error)));
+                }
+                let list = List::new(items).block(block);
                 f.render_widget(list, area);
 
                 if self.popup.visible {
-                    let mut node = &self.root;
-                    for &i in &self.focus_path {
-                        node = &node.children[i];
-                    }
-                    self.popup.draw(f, area, node);
+                    self.popup
+                        .draw(f, popup_area, &self.tree[self.focus], theme);
                 }
-            })?;
 
-            if let Some(pos) = lines.iter().position(|(_, _, p)| *p == self.focus_path) {
-                if pos < self.scroll_offset {
-                    self.scroll_offset = pos;
-                } else if pos >= self.scroll_offset + height.saturating_sub(3) {
-                    self.scroll_offset = pos.saturating_sub(height.saturating_sub(3));
+                if self.finder.visible {
+                    self.finder.draw(f, area, theme);
                 }
+
+                if self.bookmark_picker.visible {
+                    let add_key = self.config.keymap.key(Action::AddBookmark);
+                    self.bookmark_picker.draw(f, popup_area, add_key, theme);
+                }
+
+                if self.prompt.visible {
+                    self.prompt.draw(f, area);
+                }
+            })?;
+            terminal::track(terminal.get_frame().area());
+
+            if let Some(pos) = rows.iter().position(|&(id, _)| id == self.focus) {
+                self.scroll_to(pos, height);
             }
 
             if event::poll(std::time::Duration::from_millis(100))? {
                 let evt = event::read()?;
-                if let Some(output) = self.handle_event(evt, height)? {
-                    self.cleanup_terminal()?;
-                    if !output.is_empty() {
-                        println!("{}", output);
+                if let Event::Key(key) = evt
+                    && key.modifiers.contains(KeyModifiers::CONTROL)
+                {
+                    match key.code {
+                        KeyCode::Char('c') => return Ok(Exit::Interrupt),
+                        KeyCode::Char('z') => {
+                            terminal::suspend(terminal)?;
+                            continue;
+                        }
+                        _ => {}
                     }
-                    return Ok(());
+                }
+                if let Some(exit) = self.handle_event(evt, height)? {
+                    return Ok(exit);
                 }
             }
         }
     }
 
-    #[allow(clippy::only_used_in_recursion)]
-    fn walk<'a>(
-        &'a self,
-        node: &'a DirEntry,
-        prefix: String,
-        lines: &mut Vec<(String, &'a DirEntry, Vec<usize>)>,
-        path: Vec<usize>,
-    ) {
-        let marker = if node.expanded { "▼" } else { "▶" };
-        let line = if node.path == Path::new("/") {
-            format!("{} /", marker)
+    pub fn set_status(&mut self, message: String) {
+        self.status = Some(message);
+    }
+
+    fn rows(&self) -> Vec<(NodeId, usize)> {
+        let mut rows = vec![];
+        for &child in &self.tree[self.tree.root()].children {
+            self.walk(child, 0, &mut rows);
+        }
+        rows
+    }
+
+    fn walk(&self, id: NodeId, depth: usize, rows: &mut Vec<(NodeId, usize)>) {
+        if !self.filter.is_visible(id) {
+            return;
+        }
+        rows.push((id, depth));
+        if self.shows_children(id) {
+            for &child in &self.tree[id].children {
+                self.walk(child, depth + 1, rows);
+            }
+        }
+    }
+
+    fn shows_children(&self, id: NodeId) -> bool {
+        let node = &self.tree[id];
+        if self.filter.is_filtering() {
+            node.children.iter().any(|&c| self.filter.is_visible(c))
+        } else {
+            node.expanded
+        }
+    }
+
+    fn line_text(&self, id: NodeId, depth: usize) -> String {
+        let node = &self.tree[id];
+        let marker = if node.error.is_some() {
+            "✗"
+        } else if node.link_status == LinkStatus::SymlinkLoop {
+            "↻"
+        } else if self.shows_children(id) {
+            "▼"
+        } else {
+            "▶"
+        };
+        let line = if node.kind == NodeKind::Placeholder {
+            format!("  {}", node.name)
         } else if let Some(target) = &node.link_target {
             format!("{} {} -> {}", marker, node.name, target.display())
         } else {
             format!("{} {}", marker, node.name)
         };
-        lines.push((format!("{}{}", prefix, line), node, path.clone()));
-        if node.expanded {
-            for (i, child) in node.children.iter().enumerate() {
-                let mut new_path = path.clone();
-                new_path.push(i);
-                self.walk(child, format!("{}    ", prefix), lines, new_path);
-            }
+        format!("{}{}", "    ".repeat(depth), line)
+    }
+
+    fn row(&self, id: NodeId) -> Option<usize> {
+        self.rows().iter().position(|&(r, _)| r == id)
+    }
+
+    fn scroll_to(&mut self, pos: usize, height: usize) {
+        if pos < self.scroll_offset {
+            self.scroll_offset = pos;
+        } else if pos >= self.scroll_offset + height.saturating_sub(3) {
+            self.scroll_offset = pos.saturating_sub(height.saturating_sub(3));
         }
     }
 
     fn move_focus(&mut self, direction: isize, height: usize) {
-        let mut lines = vec![];
-        self.walk(&self.root, "".to_string(), &mut lines, vec![]);
+        let rows = self.rows();
 
-        let new_focus = lines
+        let new_focus = rows
             .iter()
-            .position(|(_, _, p)| *p == self.focus_path)
+            .position(|&(id, _)| id == self.focus)
             .map(|pos| {
-                let new = (pos as isize + direction).clamp(0, lines.len() as isize - 1) as usize;
-                (lines[new].2.clone(), new)
+                let new = (pos as isize + direction).clamp(0, rows.len() as isize - 1) as usize;
+                (rows[new].0, new)
             });
 
-        if let Some((path, pos)) = new_focus {
-            self.focus_path = path;
-            if pos < self.scroll_offset {
-                self.scroll_offset = pos;
-            } else if pos >= self.scroll_offset + height.saturating_sub(3) {
-                self.scroll_offset = pos.saturating_sub(height.saturating_sub(3));
-            }
+        if let Some((id, pos)) = new_focus {
+            self.focus = id;
+            self.scroll_to(pos, height);
+        }
+    }
+
+    fn update_tree<T>(&mut self, f: impl FnOnce(&mut Tree, &Visibility, &Sorter) -> T) -> T {
+        let ancestors: Vec<NodeId> = self.tree.ancestors(self.focus).collect();
+        let row = self
+            .row(self.focus)
+            .map(|pos| pos.saturating_sub(self.scroll_offset));
+
+        let result = f(&mut self.tree, &self.visibility, &self.sorter);
+
+        let root = self.tree.root();
+        self.focus = ancestors
+            .into_iter()
+            .find(|&id| id != root && self.tree.contains(id))
+            .unwrap_or(self.fs_root);
+        self.filter.update(&self.tree);
+        if let (Some(row), Some(pos)) = (row, self.row(self.focus)) {
+            self.scroll_offset = pos.saturating_sub(row);
         }
+        result
     }
 
-    fn handle_event(&mut self, event: Event, height: usize) -> io::Result<Option<String>> {
+    fn handle_event(&mut self, event: Event, height: usize) -> io::Result<Option<Exit>> {
         use KeyCode::*;
+        if let Event::Key(_) = event {
+            self.status = None;
+        }
         if self.popup.visible {
-            let mut node = &self.root;
-            for &i in &self.focus_path {
-                node = &node.children[i];
-            }
-
+            let node = &self.tree[self.focus];
             if let Event::Key(key) = event {
-                match key.code {
-                    Char('q') | Esc | Char('f') => self.popup.hide(),
-                    Char('j') | Down => self.popup.scroll_down(
+                let view_height =
+                    (height * self.config.behavior.popup_height as usize / 100).saturating_sub(2);
+                match (self.config.keymap.action(&key), key.code) {
+                    (Some(Action::ToggleFiles), _) | (_, Char('q') | Esc | Char('f')) => {
+                        self.popup.hide()
+                    }
+                    (Some(Action::Down), _) | (_, Char('j') | Down) => self.popup.scroll_down(
                         fs::read_dir(&node.path).map(|r| r.count()).unwrap_or(0),
-                        height / 2 - 2,
+                        view_height,
                     ),
-                    Char('k') | Up => self.popup.scroll_up(),
+                    (Some(Action::Up), _) | (_, Char('k') | Up) => self.popup.scroll_up(),
                     _ => {}
                 }
             }
@@ -224,150 +480,474 @@ impl App {
             return Ok(None);
         }
 
+        if self.prompt.visible {
+            if let Event::Key(key) = event {
+                self.handle_prompt_key(key);
+            }
+            return Ok(None);
+        }
+
+        if self.bookmark_picker.visible {
+            if let Event::Key(key) = event {
+                self.handle_bookmark_key(key);
+            }
+            return Ok(None);
+        }
+
+        if self.finder.visible {
+            if let Event::Key(key) = event {
+                self.handle_finder_key(key);
+            }
+            return Ok(None);
+        }
+
+        if self.filter.editing {
+            if let Event::Key(key) = event {
+                self.handle_filter_key(key, height);
+            }
+            return Ok(None);
+        }
+
         if let Event::Key(key) = event {
-            match key.code {
-                Char('q') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    return Ok(Some("".to_string()));
+            match self.config.keymap.action(&key) {
+                Some(Action::Filter) => self.filter.start(),
+                Some(Action::Cancel) if self.filter.active => self.clear_filter(),
+                Some(Action::Cancel) if self.loader.is_busy() => self.cancel_loads(),
+                Some(action @ (Action::NextMatch | Action::PrevMatch))
+                    if self.filter.is_filtering() =>
+                {
+                    let forward = action == Action::NextMatch;
+                    if let Some(hit) = self.filter.next_hit(self.focus, forward) {
+                        self.focus = hit;
+                    }
                 }
-                Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Action::Quit) => return Ok(Some(Exit::Cancel)),
+                Some(
+                    Action::ToggleHidden
+                    | Action::ToggleIgnored
+                    | Action::Refresh
+                    | Action::FollowLink
+                    | Action::Search
+                    | Action::AddBookmark
+                    | Action::Bookmarks,
+                ) if self.source != Source::Filesystem => {}
+                Some(Action::ToggleFiles) if self.source == (Source::List { stat: false }) => {}
+                Some(Action::ToggleFiles) if !self.focused().is_virtual() => {
                     self.popup.toggle();
                 }
-                Enter => {
-                    let mut node = &self.root;
-                    for &i in &self.focus_path {
-                        node = &node.children[i];
-                    }
-                    return Ok(Some(format!("cd {}", node.path.display())));
+                Some(Action::AddBookmark) if !self.focused().is_virtual() => {
+                    let node = self.focused();
+                    let name = node
+                        .path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    self.bookmark_target = Some(node.path.clone());
+                    self.prompt.open("bookmark name", &name);
                 }
-                Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.move_focus(-1, height)
+                Some(Action::Bookmarks) => {
+                    self.bookmark_picker
+                        .open(bookmarks::load().unwrap_or_default());
                 }
-                Up => self.move_focus(-1, height),
-                Char('j') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.move_focus(1, height)
+                Some(Action::Search) if !self.focused().is_virtual() => {
+                    let path = self.focused().path.clone();
+                    self.finder
+                        .open(&path, self.config.behavior.search_depth, &self.visibility);
                 }
-                Down => self.move_focus(1, height),
-                Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    let mut node = &mut self.root;
-                    for &i in &self.focus_path {
-                        node = &mut node.children[i];
-                    }
-                    node.expanded = true;
-                    node.load_children();
+                Some(Action::Select) if !self.focused().is_virtual() => {
+                    return Ok(Some(Exit::Select(self.focused().path.clone())));
                 }
-                Right => {
-                    let mut node = &mut self.root;
-                    for &i in &self.focus_path {
-                        node = &mut node.children[i];
-                    }
-                    node.expanded = true;
-                    node.load_children();
+                Some(Action::Up) => self.move_focus(-1, height),
+                Some(Action::Down) => self.move_focus(1, height),
+                Some(Action::ToggleHidden) => self.toggle_hidden(),
+                Some(Action::ToggleIgnored) => self.toggle_ignored(),
+                Some(Action::Sort) => self.cycle_sort(),
+                Some(Action::Refresh) => self.reload_async(self.focus),
+                Some(Action::FollowLink) => self.follow_link(),
+                Some(Action::Expand) if self.focused().link_status != LinkStatus::SymlinkLoop => {
+                    let focus = self.focus;
+                    self.tree[focus].expanded = true;
+                    self.expand_async(focus);
                 }
-                Char('h') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    let mut node = &mut self.root;
-                    for &i in &self.focus_path {
-                        node = &mut node.children[i];
+                Some(Action::Collapse) => {
+                    let focus = self.focus;
+                    if self.tree[focus].expanded {
+                        self.tree.collapse_all(focus);
+                    } else if let Some(parent) = self.tree[focus].parent
+                        && parent != self.tree.root()
+                    {
+                        self.focus = parent;
+                        self.expand_async(parent);
                     }
-                    if node.expanded {
-                        node.collapse_all();
-                    } else {
-                        self.focus_path.pop();
-                        let mut node = &mut self.root;
-                        for &i in &self.focus_path {
-                            node = &mut node.children[i];
-                        }
-                        node.load_children();
+                }
+                _ => {
+                    if let Char(c) = key.code
+                        && c.is_ascii_graphic()
+                        && !key.modifiers.contains(KeyModifiers::CONTROL)
+                    {
+                        self.handle_char_jump(c);
                     }
                 }
-                Left => {
-                    let mut node = &mut self.root;
-                    for &i in &self.focus_path {
-                        node = &mut node.children[i];
+            }
+        }
+
+        if self.filter.active {
+            self.filter.update(&self.tree);
+        }
+
+        Ok(None)
+    }
+
+    fn nav_action(&self, key: &KeyEvent) -> Option<Action> {
+        let typed = matches!(key.code, KeyCode::Char(_))
+            && !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if typed {
+            None
+        } else {
+            self.config.keymap.action(key)
+        }
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent, height: usize) {
+        use KeyCode::*;
+        match self.nav_action(&key) {
+            Some(Action::Up) => return self.move_focus(-1, height),
+            Some(Action::Down) => return self.move_focus(1, height),
+            _ => {}
+        }
+        match key.code {
+            Esc => self.clear_filter(),
+            Enter => self.filter.editing = false,
+            Backspace => {
+                if self.filter.query.pop().is_none() {
+                    self.clear_filter();
+                } else {
+                    self.update_filter_focus();
+                }
+            }
+            Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.filter.query.push(c);
+                self.update_filter_focus();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_finder_key(&mut self, key: KeyEvent) {
+        use KeyCode::*;
+        match self.nav_action(&key) {
+            Some(Action::Up) => return self.finder.move_selection(-1),
+            Some(Action::Down) => return self.finder.move_selection(1),
+            _ => {}
+        }
+        match key.code {
+            Esc => self.finder.close(),
+            Enter => {
+                if let Some(target) = self.finder.selected_path().map(Path::to_path_buf) {
+                    self.reveal(&target);
+                }
+                self.finder.close();
+            }
+            Backspace => self.finder.pop_char(),
+            Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => self.finder.push_char(c),
+            _ => {}
+        }
+    }
+
+    fn handle_prompt_key(&mut self, key: KeyEvent) {
+        use KeyCode::*;
+        match key.code {
+            Esc => {
+                self.prompt.hide();
+                self.bookmark_target = None;
+            }
+            Enter => {
+                let Some(target) = self.bookmark_target.take() else {
+                    self.prompt.hide();
+                    return;
+                };
+                match bookmarks::add(&self.prompt.input, &target) {
+                    Ok(()) => {
+                        self.prompt.hide();
+                        self.reload_bookmarks();
                     }
-                    if node.expanded {
-                        node.collapse_all();
-                    } else {
-                        self.focus_path.pop();
-                        let mut node = &mut self.root;
-                        for &i in &self.focus_path {
-                            node = &mut node.children[i];
-                        }
-                        node.load_children();
+                    Err(e) => {
+                        let input = self.prompt.input.clone();
+                        self.prompt.open(&e.to_string(), &input);
+                        self.bookmark_target = Some(target);
                     }
                 }
-                Char(c) if c.is_ascii_graphic() => {
-                    self.handle_char_jump(c);
+            }
+            Backspace => {
+                self.prompt.input.pop();
+            }
+            Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.prompt.input.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_bookmark_key(&mut self, key: KeyEvent) {
+        use KeyCode::*;
+        match self.config.keymap.action(&key) {
+            Some(Action::Bookmarks) => return self.bookmark_picker.hide(),
+            Some(Action::Up) => return self.bookmark_picker.move_selection(-1),
+            Some(Action::Down) => return self.bookmark_picker.move_selection(1),
+            _ => {}
+        }
+        match key.code {
+            Esc | Char('q') => self.bookmark_picker.hide(),
+            Enter => {
+                let Some(bookmark) = self.bookmark_picker.selected().cloned() else {
+                    return;
+                };
+                if bookmark.path.starts_with(&self.tree[self.fs_root].path) {
+                    self.reveal(&bookmark.path);
+                } else if let Some(id) = self.tree[self.bookmarks_root]
+                    .children
+                    .iter()
+                    .copied()
+                    .find(|&c| self.tree[c].name == bookmark.name)
+                {
+                    self.tree[self.bookmarks_root].expanded = true;
+                    self.focus = id;
                 }
-                _ => {}
+                self.bookmark_picker.hide();
+            }
+            Delete => self.remove_selected_bookmark(),
+            Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.remove_selected_bookmark()
             }
+            _ => {}
         }
+    }
 
-        Ok(None)
+    fn reveal(&mut self, target: &Path) {
+        self.visibility.pin(target);
+        let node = self.deepest_toward(target);
+        if self.tree[node].path != target && self.tree[node].is_loaded {
+            self.load_async(node);
+        }
+        self.reveal_target = Some(target.to_path_buf());
+        self.continue_reveal();
     }
 
-    fn handle_char_jump(&mut self, c: char) {
-        use std::time::{Duration, Instant};
+    fn continue_reveal(&mut self) {
+        let Some(target) = self.reveal_target.clone() else {
+            return;
+        };
+        let node = self.deepest_toward(&target);
+        let root = self.tree.root();
+        for id in self
+            .tree
+            .ancestors(node)
+            .take_while(|&id| id != root)
+            .collect::<Vec<_>>()
+        {
+            self.tree[id].expanded = true;
+        }
+        self.focus = node;
+        if self.tree[node].path == target {
+            self.reveal_target = None;
+        } else if !self.tree[node].is_loaded && !self.loader.is_loading(node) {
+            self.load_async(node);
+        } else if !self.loader.is_loading(node) {
+            self.reveal_target = None;
+            self.status = Some(format!("cannot open {}", target.display()));
+        }
+        self.filter.update(&self.tree);
+    }
 
-        let now = Instant::now();
-        let timeout = Duration::from_millis(1000);
+    fn deepest_toward(&self, target: &Path) -> NodeId {
+        let mut node = self.fs_root;
+        while let Some(child) = self.tree[node].children.iter().copied().find(|&c| {
+            self.tree[c].kind != NodeKind::Placeholder && target.starts_with(&self.tree[c].path)
+        }) {
+            node = child;
+        }
+        node
+    }
 
-        if let Some(last) = self.last_input_time {
-            if now.duration_since(last) > timeout {
-                self.search_buffer.clear();
-            }
+    fn follow_link(&mut self) {
+        let node = self.focused();
+        if node.link_target.is_none() {
+            return;
+        }
+        if let Ok(target) = node.path.canonicalize() {
+            self.reveal(&target);
         }
+    }
 
-        self.search_buffer.push(c);
-        self.last_input_time = Some(now);
+    fn toggle_hidden(&mut self) {
+        self.visibility.show_hidden = !self.visibility.show_hidden;
+        self.reload_async(self.tree.root());
+    }
 
-        let query = self.search_buffer.to_lowercase();
+    fn toggle_ignored(&mut self) {
+        self.visibility.show_ignored = !self.visibility.show_ignored;
+        self.reload_async(self.tree.root());
+    }
 
-        let mut node = &self.root;
-        for &i in &self.focus_path {
-            node = &node.children[i];
+    fn load_async(&mut self, id: NodeId) {
+        let node = &self.tree[id];
+        if node.is_virtual() || self.source != Source::Filesystem {
+            return;
         }
+        let known = self.tree.child_paths(id);
+        self.loader
+            .start(id, node.path.clone(), known, self.visibility.clone());
+        self.tree.set_placeholder(id, "loading…");
+    }
 
-        let mut lines = vec![];
-        self.walk(node, "".to_string(), &mut lines, self.focus_path.clone());
+    fn expand_async(&mut self, id: NodeId) {
+        if !self.tree[id].is_loaded && !self.loader.is_loading(id) {
+            self.load_async(id);
+        }
+    }
 
-        if let Some(parent_path) = self
-            .focus_path
-            .get(..self.focus_path.len().saturating_sub(1))
-        {
-            let mut parent = &self.root;
-            for &i in parent_path {
-                parent = &parent.children[i];
+    fn reload_async(&mut self, id: NodeId) {
+        for id in self.tree.loaded_in(id) {
+            self.load_async(id);
+        }
+    }
+
+    fn cancel_loads(&mut self) {
+        self.reveal_target = None;
+        for id in self.loader.cancel_all() {
+            if self.tree.contains(id) {
+                self.tree
+                    .stop_loading(id, "loading cancelled", &self.sorter);
             }
-            for (i, sibling) in parent.children.iter().enumerate() {
-                if i != *self.focus_path.last().unwrap_or(&0) {
-                    lines.push((sibling.name.clone(), sibling, {
-                        let mut p = parent_path.to_vec();
-                        p.push(i);
-                        p
-                    }));
+        }
+    }
+
+    fn poll_loader(&mut self) {
+        let updates = self.loader.poll();
+        if updates.is_empty() {
+            return;
+        }
+        if updates.iter().any(|u| matches!(u, LoadUpdate::TimedOut(_))) {
+            self.reveal_target = None;
+        }
+        let timeout = self.config.behavior.load_timeout_ms as f64 / 1000.0;
+        self.update_tree(|tree, _, sorter| {
+            for update in updates {
+                match update {
+                    LoadUpdate::Entries(id, entries) if tree.contains(id) => {
+                        tree.append_children(id, entries)
+                    }
+                    LoadUpdate::Done(id, seen) if tree.contains(id) => {
+                        tree.finish_load(id, &seen, sorter)
+                    }
+                    LoadUpdate::Failed(id, error) if tree.contains(id) => tree.fail_load(id, error),
+                    LoadUpdate::TimedOut(id) if tree.contains(id) => {
+                        tree.stop_loading(id, &format!("timed out after {}s", timeout), sorter)
+                    }
+                    _ => {}
                 }
             }
+        });
+        self.continue_reveal();
+    }
+
+    fn poll_watcher(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        watcher.sync(self.tree.expanded_dirs());
+        let tree = &self.tree;
+        let changed = watcher.changed(|path| tree.contains_path(path));
+        for dir in changed {
+            let ids = self.tree.loaded_at(&dir);
+            if ids.iter().any(|&id| self.loader.is_loading(id)) {
+                if let Some(watcher) = &mut self.watcher {
+                    watcher.defer(dir);
+                }
+                continue;
+            }
+            for id in ids {
+                self.load_async(id);
+            }
         }
+    }
 
-        if let Some((_, _, path)) = lines
-            .iter()
-            .find(|(name, _, _)| name.to_lowercase().starts_with(&query))
+    fn cycle_sort(&mut self) {
+        self.sorter.cycle();
+        if let Err(e) = self.sorter.mode.save() {
+            self.status = Some(format!("cannot save the sort order: {}", e));
+        }
+        self.update_tree(|tree, _, sorter| tree.resort(tree.root(), sorter));
+    }
+
+    fn remove_selected_bookmark(&mut self) {
+        if let Some(bookmark) = self.bookmark_picker.selected()
+            && bookmarks::remove(&bookmark.name).is_ok()
         {
-            self.focus_path = path.clone();
+            self.bookmark_picker
+                .set_bookmarks(bookmarks::load().unwrap_or_default());
+            self.reload_bookmarks();
+        }
+    }
+
+    fn reload_bookmarks(&mut self) {
+        let bookmarks_root = self.bookmarks_root;
+        self.update_tree(|tree, _, _| tree.set_children(bookmarks_root, bookmark_entries()));
+    }
+
+    fn update_filter_focus(&mut self) {
+        self.filter.update(&self.tree);
+        if let Some(best) = self.filter.best() {
+            self.focus = best;
         }
     }
 
-    fn init_terminal(&self) -> io::Result<Terminal<CrosstermBackend<Stderr>>> {
-        enable_raw_mode()?;
-        let mut stderr = io::stderr();
-        execute!(stderr, EnterAlternateScreen)?;
-        let backend = CrosstermBackend::new(stderr);
-        Terminal::new(backend)
+    fn clear_filter(&mut self) {
+        self.filter.clear();
+        let ancestors: Vec<NodeId> = self.tree.ancestors(self.focus).skip(1).collect();
+        for id in ancestors {
+            self.tree[id].expanded = true;
+        }
     }
 
-    fn cleanup_terminal(&self) -> io::Result<()> {
-        disable_raw_mode()?;
-        execute!(io::stderr(), LeaveAlternateScreen)?;
-        Ok(())
+    fn focused(&self) -> &DirEntry {
+        &self.tree[self.focus]
     }
+
+    fn handle_char_jump(&mut self, c: char) {
+        let now = Instant::now();
+        let timeout = Duration::from_millis(self.config.behavior.jump_timeout_ms);
+
+        if let Some(last) = self.last_input_time
+            && now.duration_since(last) > timeout
+        {
+            self.search_buffer.clear();
+        }
+
+        self.search_buffer.push(c);
+        self.last_input_time = Some(now);
+
+        let query = self.search_buffer.to_lowercase();
+
+        let Some(parent) = self.tree[self.focus].parent else {
+            return;
+        };
+        if let Some(&sibling) = self.tree[parent]
+            .children
+            .iter()
+            .find(|&&c| c != self.focus && self.tree[c].name.to_lowercase().starts_with(&query))
+        {
+            self.focus = sibling;
+        }
+    }
+}
+
+fn bookmark_entries() -> Vec<DirEntry> {
+    bookmarks::load()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|b| DirEntry::new_link(&b.name, b.path))
+        .collect()
 }
@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+use crate::config::Theme;
+use crate::fuzzy::fuzzy_match;
+use crate::tree::{NodeId, Tree};
+
+pub struct Filter {
+    pub active: bool,
+    pub editing: bool,
+    pub query: String,
+    matches: HashMap<NodeId, Vec<usize>>,
+    visible: HashSet<NodeId>,
+    order: HashMap<NodeId, usize>,
+    hits: Vec<NodeId>,
+    best: Option<NodeId>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self {
+            active: false,
+            editing: false,
+            query: String::new(),
+            matches: HashMap::new(),
+            visible: HashSet::new(),
+            order: HashMap::new(),
+            hits: vec![],
+            best: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.active = true;
+        self.editing = true;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn is_filtering(&self) -> bool {
+        self.active && !self.query.is_empty()
+    }
+
+    pub fn update(&mut self, tree: &Tree) {
+        self.matches.clear();
+        self.visible.clear();
+        self.order.clear();
+        self.hits.clear();
+        self.best = None;
+        if !self.is_filtering() {
+            return;
+        }
+
+        let mut best_score = None;
+        let mut stack: Vec<NodeId> = tree[tree.root()].children.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let node = &tree[id];
+            self.order.insert(id, self.order.len());
+            if !node.is_virtual()
+                && let Some(m) = fuzzy_match(&self.query, &node.name)
+            {
+                let key = (m.score, std::cmp::Reverse(node.name.len()));
+                if best_score.is_none_or(|best| key > best) {
+                    best_score = Some(key);
+                    self.best = Some(id);
+                }
+                self.visible.extend(tree.ancestors(id));
+                self.hits.push(id);
+                self.matches.insert(id, m.positions);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+    }
+
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        !self.is_filtering() || self.visible.contains(&id)
+    }
+
+    pub fn best(&self) -> Option<NodeId> {
+        self.best
+    }
+
+    pub fn next_hit(&self, current: NodeId, forward: bool) -> Option<NodeId> {
+        let rank = |id: &NodeId| self.order.get(id).copied();
+        let current = rank(&current);
+        if forward {
+            self.hits
+                .iter()
+                .find(|h| rank(h) > current)
+                .or(self.hits.first())
+                .copied()
+        } else {
+            self.hits
+                .iter()
+                .rev()
+                .find(|h| rank(h) < current)
+                .or(self.hits.last())
+                .copied()
+        }
+    }
+
+    pub fn status_line(&self, current: NodeId, theme: &Theme) -> Line<'static> {
+        let cursor = if self.editing { "▏" } else { "" };
+        let count = match self.hits.iter().position(|&h| h == current) {
+            Some(i) => format!("[{}/{}]", i + 1, self.hits.len()),
+            None => format!("[{}]", self.hits.len()),
+        };
+        Line::from(vec![
+            Span::raw(format!(" /{}{} ", self.query, cursor)),
+            Span::styled(format!("{} ", count), Style::default().fg(theme.border)),
+        ])
+    }
+
+    pub fn highlight(
+        &self,
+        text: &str,
+        name_start: usize,
+        id: NodeId,
+        color: Color,
+    ) -> Line<'static> {
+        let Some(positions) = self.matches.get(&id).filter(|_| self.is_filtering()) else {
+            return Line::from(text.to_string());
+        };
+
+        highlight(text, name_start, positions, color)
+    }
+}
+
+pub fn highlight(text: &str, offset: usize, positions: &[usize], color: Color) -> Line<'static> {
+    let matched = Style::default().fg(color).add_modifier(Modifier::BOLD);
+    let mut spans = vec![];
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_match = i >= offset && positions.contains(&(i - offset));
+        if is_match != run_matched && !run.is_empty() {
+            let style = if run_matched {
+                matched
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_matched = is_match;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let style = if run_matched {
+            matched
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(run, style));
+    }
+    Line::from(spans)
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
+
+use crate::app::filter::highlight;
+use crate::config::Theme;
+use crate::fuzzy::Match;
+use crate::search::{Search, SearchEvent};
+use crate::visibility::Visibility;
+
+const MAX_HITS: usize = 500;
+
+struct Hit {
+    path: PathBuf,
+    relative: String,
+    matched: Match,
+}
+
+pub struct Finder {
+    pub visible: bool,
+    root: PathBuf,
+    max_depth: usize,
+    visibility: Option<Visibility>,
+    query: String,
+    hits: Vec<Hit>,
+    selected: usize,
+    scanned: usize,
+    done: bool,
+    search: Option<Search>,
+}
+
+impl Finder {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            root: PathBuf::new(),
+            max_depth: 0,
+            visibility: None,
+            query: String::new(),
+            hits: vec![],
+            selected: 0,
+            scanned: 0,
+            done: false,
+            search: None,
+        }
+    }
+
+    pub fn open(&mut self, root: &Path, max_depth: usize, visibility: &Visibility) {
+        *self = Self::new();
+        self.visible = true;
+        self.root = root.to_path_buf();
+        self.max_depth = max_depth;
+        self.visibility = Some(visibility.clone());
+    }
+
+    pub fn close(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.restart();
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.restart();
+    }
+
+    fn restart(&mut self) {
+        self.hits.clear();
+        self.selected = 0;
+        self.scanned = 0;
+        self.done = false;
+        self.search = None;
+        if !self.query.is_empty()
+            && let Some(visibility) = &self.visibility
+        {
+            self.search = Some(Search::spawn(
+                self.root.clone(),
+                self.query.clone(),
+                self.max_depth,
+                visibility.clone(),
+            ));
+        }
+    }
+
+    pub fn poll(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let mut finished = false;
+        for event in search.events() {
+            match event {
+                SearchEvent::Found(path, relative, matched) => {
+                    let key = |h: &Hit| (-h.matched.score, h.relative.len());
+                    let hit = Hit {
+                        path,
+                        relative,
+                        matched,
+                    };
+                    let pos = self.hits.partition_point(|h| key(h) <= key(&hit));
+                    if pos < MAX_HITS {
+                        self.hits.insert(pos, hit);
+                        self.hits.truncate(MAX_HITS);
+                    }
+                }
+                SearchEvent::Progress(scanned) => self.scanned = scanned,
+                SearchEvent::Done(scanned) => {
+                    self.scanned = scanned;
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.done = true;
+            self.search = None;
+        }
+    }
+
+    pub fn move_selection(&mut self, direction: isize) {
+        if self.hits.is_empty() {
+            return;
+        }
+        self.selected =
+            (self.selected as isize + direction).clamp(0, self.hits.len() as isize - 1) as usize;
+    }
+
+    pub fn selected_path(&self) -> Option<&Path> {
+        self.hits.get(self.selected).map(|h| h.path.as_path())
+    }
+
+    pub fn draw(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let popup_area = Rect {
+            x: area.x + area.width / 8,
+            y: area.y + area.height / 8,
+            width: area.width * 3 / 4,
+            height: area.height * 3 / 4,
+        }
+        .intersection(area);
+        f.render_widget(Clear, popup_area);
+
+        let status = if self.query.is_empty() {
+            "type to search".to_string()
+        } else if self.done {
+            format!("{} found, {} dirs scanned", self.hits.len(), self.scanned)
+        } else {
+            format!("{} found, {} dirs scanned…", self.hits.len(), self.scanned)
+        };
+        let block = Block::default()
+            .title(format!("search {}", self.root.display()))
+            .title_alignment(Alignment::Center)
+            .title_bottom(Line::from(vec![
+                Span::raw(format!(" > {}▏ ", self.query)),
+                Span::styled(format!("{} ", status), Style::default().fg(theme.border)),
+            ]))
+            .borders(Borders::ALL);
+
+        let view_height = popup_area.height.saturating_sub(2) as usize;
+        let offset = (self.selected + 1).saturating_sub(view_height);
+
+        let items: Vec<ListItem> = self
+            .hits
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(view_height)
+            .map(|(i, hit)| {
+                let mut style = Style::default();
+                if i == self.selected {
+                    style = style.bg(theme.focus_bg).fg(theme.focus_fg);
+                }
+                let line = highlight(&hit.relative, 0, &hit.matched.positions, theme.matched);
+                ListItem::new(line).style(style)
+            })
+            .collect();
+
+        f.render_widget(List::new(items).block(block), popup_area);
+    }
+}
@@ -1,9 +1,10 @@
-use crate::tree::DirEntry;
+use crate::config::Theme;
+use crate::tree::{DirEntry, LoadError};
 use chrono::{DateTime, Local};
 use ratatui::{
     Frame,
     layout::{Alignment, Rect},
-    style::{Color, Style},
+    style::Style,
     widgets::{Block, Borders, Clear, List, ListItem},
 };
 use std::fs;
@@ -46,13 +47,7 @@ impl Popup {
         }
     }
 
-    pub fn draw(&self, f: &mut Frame, area: Rect, node: &DirEntry) {
-        let popup_area = Rect {
-            x: area.width / 4,
-            y: area.height / 4,
-            width: area.width / 2,
-            height: area.height / 2,
-        };
+    pub fn draw(&self, f: &mut Frame, popup_area: Rect, node: &DirEntry, theme: &Theme) {
         f.render_widget(Clear, popup_area);
 
         let block = Block::default()
@@ -67,10 +62,13 @@ impl Popup {
 
                 files
                     .into_iter()
-                    .filter_map(|e| Self::format_file_item(&e))
+                    .filter_map(|e| Self::format_file_item(&e, theme))
                     .collect()
             }
-            Err(_) => vec![ListItem::new("reading error")],
+            Err(e) => vec![
+                ListItem::new(format!("cannot read: {}", LoadError::from(e)))
+                    .style(Style::default().fg(theme.error)),
+            ],
         };
 
         let view_height = popup_area.height.saturating_sub(2) as usize;
@@ -86,7 +84,7 @@ impl Popup {
         f.render_widget(file_list, popup_area);
     }
 
-    fn format_file_item(entry: &fs::DirEntry) -> Option<ListItem<'static>> {
+    fn format_file_item(entry: &fs::DirEntry, theme: &Theme) -> Option<ListItem<'static>> {
         let path = entry.path();
         let file_name = entry.file_name().to_string_lossy().to_string();
         let meta = fs::symlink_metadata(&path).ok()?;
@@ -107,19 +105,19 @@ impl Popup {
                         .unwrap_or_else(|| "???".into());
                     (
                         format!("{} -> {}", file_name, link_str),
-                        Style::default().fg(Color::Cyan),
+                        Style::default().fg(theme.symlink),
                     )
                 }
                 Err(_) => (
                     format!("{} -> ???", file_name),
-                    Style::default().fg(Color::Red),
+                    Style::default().fg(theme.broken),
                 ),
             }
         } else if meta.is_file() {
             let style = if meta.permissions().mode() & 0o111 != 0 {
-                Style::default().fg(Color::Green)
+                Style::default().fg(theme.executable)
             } else {
-                Style::default().fg(Color::White)
+                Style::default().fg(theme.file)
             };
             (file_name, style)
         } else {
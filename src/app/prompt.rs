@@ -0,0 +1,49 @@
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+pub struct Prompt {
+    pub visible: bool,
+    pub input: String,
+    title: String,
+}
+
+impl Prompt {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            input: String::new(),
+            title: String::new(),
+        }
+    }
+
+    pub fn open(&mut self, title: &str, input: &str) {
+        self.visible = true;
+        self.title = title.to_string();
+        self.input = input.to_string();
+    }
+
+    pub fn hide(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn draw(&self, f: &mut Frame, area: Rect) {
+        let prompt_area = Rect {
+            x: area.x + area.width / 4,
+            y: area.y + area.height.saturating_sub(3) / 2,
+            width: area.width / 2,
+            height: 3.min(area.height),
+        }
+        .intersection(area);
+        f.render_widget(Clear, prompt_area);
+
+        let block = Block::default()
+            .title(self.title.as_str())
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL);
+        let input = Paragraph::new(format!("{}▏", self.input)).block(block);
+        f.render_widget(input, prompt_area);
+    }
+}
@@ -0,0 +1,106 @@
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::{paths, store};
+
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+fn bookmarks_file() -> io::Result<PathBuf> {
+    Ok(paths::config_dir()?.join("bookmarks"))
+}
+
+pub fn validate_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name.starts_with('#') || name.contains(['\t', '\n']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "invalid bookmark name {:?}: must be non-empty, not start with '#' and not contain tabs or newlines",
+                name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+pub fn load() -> io::Result<Vec<Bookmark>> {
+    let file = bookmarks_file()?;
+    let lock = store::lock_file(&file)?;
+    lock.lock_shared()?;
+    read(&file)
+}
+
+pub fn update<T>(f: impl FnOnce(&mut Vec<Bookmark>) -> T) -> io::Result<T> {
+    let file = bookmarks_file()?;
+    let lock = store::lock_file(&file)?;
+    lock.lock()?;
+    let mut bookmarks = read(&file)?;
+    let result = f(&mut bookmarks);
+    write(&file, &bookmarks)?;
+    Ok(result)
+}
+
+pub fn add(name: &str, path: &Path) -> io::Result<()> {
+    validate_name(name)?;
+    if path.as_os_str().as_bytes().contains(&b'\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "paths containing newlines cannot be bookmarked",
+        ));
+    }
+    update(|bookmarks| {
+        let bookmark = Bookmark {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+        };
+        match bookmarks.iter_mut().find(|b| b.name == name) {
+            Some(existing) => *existing = bookmark,
+            None => bookmarks.push(bookmark),
+        }
+    })
+}
+
+pub fn remove(name: &str) -> io::Result<bool> {
+    update(|bookmarks| {
+        let before = bookmarks.len();
+        bookmarks.retain(|b| b.name != name);
+        bookmarks.len() != before
+    })
+}
+
+fn read(file: &Path) -> io::Result<Vec<Bookmark>> {
+    Ok(store::read(file)?
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty() && !line.starts_with(b"#"))
+        .map(|line| match line.iter().position(|&b| b == b'\t') {
+            Some(i) => Bookmark {
+                name: String::from_utf8_lossy(&line[..i]).to_string(),
+                path: PathBuf::from(OsStr::from_bytes(&line[i + 1..])),
+            },
+            None => {
+                let path = PathBuf::from(OsStr::from_bytes(line));
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                Bookmark {
+                    name: name.to_string_lossy().to_string(),
+                    path,
+                }
+            }
+        })
+        .collect())
+}
+
+fn write(file: &Path, bookmarks: &[Bookmark]) -> io::Result<()> {
+    let mut data = vec![];
+    for bookmark in bookmarks {
+        data.extend_from_slice(bookmark.name.as_bytes());
+        data.push(b'\t');
+        data.extend_from_slice(bookmark.path.as_os_str().as_bytes());
+        data.push(b'\n');
+    }
+    store::write_atomic(file, &data)
+}
@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::output::{Output, Template};
+use crate::shell::Shell;
+use crate::terminal::Height;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "cda",
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    after_help = "Exit status:\n  0    a directory was selected\n  1    cancelled without a selection\n  2    invalid usage, configuration or other error\n  130  interrupted by Ctrl+C or a signal"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Directory to start from [default: current directory]
+    pub path: Option<PathBuf>,
+
+    /// Do not show anything above this directory
+    #[arg(long, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+
+    /// Print the selected path instead of a cd command
+    #[arg(short, long, conflicts_with = "output")]
+    pub print_path: bool,
+
+    /// What to print on exit: a cd command, the bare path or a JSON object
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub output: Option<Output>,
+
+    /// Print the selection using a template of {path}, {relpath} and {name};
+    /// use {q:FIELD} to quote a field for the shell
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["output", "print_path"])]
+    pub format: Option<Template>,
+
+    /// Browse the paths read from stdin as a tree instead of the filesystem
+    #[arg(short, long)]
+    pub list: bool,
+
+    /// Check the paths given with --list on disk to show symlinks and missing paths
+    #[arg(long, requires = "list")]
+    pub stat: bool,
+
+    /// Read NUL-separated paths from stdin instead of one per line
+    #[arg(long)]
+    pub read0: bool,
+
+    /// Terminate the output with NUL instead of a newline
+    #[arg(short = '0', long)]
+    pub null: bool,
+
+    /// Draw below the cursor in N lines or N% of the terminal instead of fullscreen
+    #[arg(long, value_name = "N[%]")]
+    pub height: Option<Height>,
+
+    /// Shell to quote the cd command for [default: $CDA_SHELL or $SHELL]
+    #[arg(long, value_enum)]
+    pub shell: Option<Shell>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print the shell integration script for SHELL
+    Init {
+        #[arg(value_enum)]
+        shell: Shell,
+
+        /// Run the cd command immediately instead of inserting it into the prompt
+        #[arg(long)]
+        exec: bool,
+    },
+
+    /// List or edit bookmarks
+    Bookmark {
+        #[command(subcommand)]
+        command: Option<BookmarkCommand>,
+    },
+
+    /// Show or maintain the history of selected directories
+    History {
+        #[command(subcommand)]
+        command: Option<HistoryCommand>,
+    },
+
+    /// Inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommand>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Validate the configuration file
+    Check,
+    /// Print the path of the configuration file
+    Path,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// List directories, most frecent first
+    List {
+        /// Show the frecency score of each directory
+        #[arg(short, long)]
+        scores: bool,
+    },
+
+    /// Remove directories that no longer exist
+    Prune {
+        /// Also remove directories not visited for DAYS days
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+    },
+
+    /// Print the history in the z/zoxide `path|rank|time` format
+    Export,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BookmarkCommand {
+    /// Bookmark a directory, replacing any bookmark with the same name
+    Add {
+        /// Bookmark name [default: name of the directory]
+        name: Option<String>,
+
+        /// Directory to bookmark [default: current directory]
+        path: Option<PathBuf>,
+    },
+
+    /// Remove a bookmark
+    #[command(alias = "remove")]
+    Rm { name: String },
+
+    /// List bookmarks as `name<TAB>path`
+    #[command(alias = "list")]
+    Ls,
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+
+use crate::keymap::{Action, KeyChord, Keymap};
+use crate::paths;
+use crate::sort::SortMode;
+use crate::visibility::Visibility;
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub behavior: Behavior,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    keymap: HashMap<Action, Vec<KeyChord>>,
+    theme: Theme,
+    behavior: Behavior,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(deserialize_with = "color")]
+    pub focus_bg: Color,
+    #[serde(deserialize_with = "color")]
+    pub focus_fg: Color,
+    #[serde(deserialize_with = "color")]
+    pub symlink: Color,
+    #[serde(deserialize_with = "color")]
+    pub broken: Color,
+    #[serde(deserialize_with = "color")]
+    pub error: Color,
+    #[serde(deserialize_with = "color")]
+    pub virtual_root: Color,
+    #[serde(deserialize_with = "color")]
+    pub matched: Color,
+    #[serde(deserialize_with = "color")]
+    pub border: Color,
+    #[serde(deserialize_with = "color")]
+    pub file: Color,
+    #[serde(deserialize_with = "color")]
+    pub executable: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            focus_bg: Color::Rgb(40, 40, 40),
+            focus_fg: Color::White,
+            symlink: Color::Cyan,
+            broken: Color::Red,
+            error: Color::LightRed,
+            virtual_root: Color::Magenta,
+            matched: Color::Yellow,
+            border: Color::Gray,
+            file: Color::White,
+            executable: Color::Green,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Behavior {
+    pub jump_timeout_ms: u64,
+    pub popup_width: u16,
+    pub popup_height: u16,
+    pub recent_limit: usize,
+    pub search_depth: usize,
+    pub show_hidden: bool,
+    pub gitignore: bool,
+    pub ignore: Vec<String>,
+    pub sort: Option<SortMode>,
+    pub watch: bool,
+    pub load_timeout_ms: u64,
+}
+
+impl Default for Behavior {
+    fn default() -> Self {
+        Self {
+            jump_timeout_ms: 1000,
+            popup_width: 50,
+            popup_height: 50,
+            recent_limit: 20,
+            search_depth: 12,
+            show_hidden: false,
+            gitignore: false,
+            ignore: vec![],
+            sort: None,
+            watch: true,
+            load_timeout_ms: 5000,
+        }
+    }
+}
+
+impl Behavior {
+    pub fn visibility(&self) -> Visibility {
+        Visibility::new(self.show_hidden, self.gitignore, &self.ignore)
+            .expect("ignore globs are validated when the config is parsed")
+    }
+
+    pub fn sort_mode(&self) -> SortMode {
+        self.sort
+            .or_else(SortMode::saved)
+            .unwrap_or(SortMode::Natural)
+    }
+
+    pub fn popup_area(&self, area: Rect) -> Rect {
+        let width = (area.width as u32 * self.popup_width as u32 / 100) as u16;
+        let height = (area.height as u32 * self.popup_height as u32 / 100) as u16;
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(|_| {
+        serde::de::Error::custom(format!(
+            "invalid color {:?}: expected a name like \"cyan\", an index 0-255 or \"#rrggbb\"",
+            s
+        ))
+    })
+}
+
+impl Config {
+    pub fn path() -> io::Result<PathBuf> {
+        Ok(paths::config_dir()?.join("config.toml"))
+    }
+
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = Self::path().map_err(|e| ConfigError {
+            path: PathBuf::from("config.toml"),
+            message: e.to_string(),
+        })?;
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&path, &text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(ConfigError {
+                path,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    pub fn parse(path: &Path, text: &str) -> Result<Self, ConfigError> {
+        let error = |message: String| ConfigError {
+            path: path.to_path_buf(),
+            message,
+        };
+        let raw: RawConfig = toml::from_str(text).map_err(|e| error(e.to_string()))?;
+
+        let keymap = Keymap::new(&raw.keymap).map_err(|e| error(format!("[keymap] {}", e)))?;
+        for (name, value) in [
+            ("popup_width", raw.behavior.popup_width),
+            ("popup_height", raw.behavior.popup_height),
+        ] {
+            if !(10..=100).contains(&value) {
+                return Err(error(format!(
+                    "[behavior] {} must be between 10 and 100 (percent), got {}",
+                    name, value
+                )));
+            }
+        }
+        if raw.behavior.search_depth == 0 {
+            return Err(error(
+                "[behavior] search_depth must be at least 1".to_string(),
+            ));
+        }
+
+        Visibility::new(false, false, &raw.behavior.ignore)
+            .map_err(|e| error(format!("[behavior] {}", e)))?;
+
+        Ok(Self {
+            keymap,
+            theme: raw.theme,
+            behavior: raw.behavior,
+        })
+    }
+}
@@ -0,0 +1,195 @@
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY: i64 = SCORE_MATCH / 2;
+const BONUS_BOUNDARY_WHITE: i64 = BONUS_BOUNDARY + 2;
+const BONUS_BOUNDARY_DELIMITER: i64 = BONUS_BOUNDARY + 1;
+const BONUS_NON_WORD: i64 = SCORE_MATCH / 2;
+const BONUS_CAMEL_123: i64 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+const BONUS_CONSECUTIVE: i64 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CharClass {
+    White,
+    NonWord,
+    Delimiter,
+    Lower,
+    Upper,
+    Letter,
+    Number,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::White
+    } else if matches!(c, '/' | ',' | ':' | ';' | '|') {
+        CharClass::Delimiter
+    } else if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Number
+    } else if c.is_alphabetic() {
+        CharClass::Letter
+    } else {
+        CharClass::NonWord
+    }
+}
+
+fn bonus_for(prev: CharClass, class: CharClass) -> i64 {
+    use CharClass::*;
+    if class > NonWord {
+        match prev {
+            White => return BONUS_BOUNDARY_WHITE,
+            Delimiter => return BONUS_BOUNDARY_DELIMITER,
+            NonWord => return BONUS_BOUNDARY,
+            _ => {}
+        }
+    }
+    if (prev == Lower && class == Upper) || (prev != Number && class == Number) {
+        BONUS_CAMEL_123
+    } else if class == NonWord || class == Delimiter {
+        BONUS_NON_WORD
+    } else if class == White {
+        BONUS_BOUNDARY_WHITE
+    } else {
+        0
+    }
+}
+
+/// Matches `pattern` as a subsequence of `text` and scores it like fzf's v1
+/// algorithm. Matching is case-insensitive unless `pattern` has uppercase.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let pattern: Vec<char> = pattern.chars().map(fold).collect();
+    let text: Vec<char> = text.chars().collect();
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: vec![],
+        });
+    }
+
+    let mut pidx = 0;
+    let mut end = None;
+    for (i, &c) in text.iter().enumerate() {
+        if fold(c) == pattern[pidx] {
+            pidx += 1;
+            if pidx == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut start = end;
+    let mut pidx = pattern.len();
+    for i in (0..=end).rev() {
+        if fold(text[i]) == pattern[pidx - 1] {
+            pidx -= 1;
+            if pidx == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut score = 0;
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut pidx = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first_bonus = 0;
+    let mut prev_class = if start > 0 {
+        char_class(text[start - 1])
+    } else {
+        CharClass::White
+    };
+
+    for (i, &c) in text.iter().enumerate().take(end + 1).skip(start) {
+        let class = char_class(c);
+        if pidx < pattern.len() && fold(c) == pattern[pidx] {
+            positions.push(i);
+            score += SCORE_MATCH;
+            let mut bonus = bonus_for(prev_class, class);
+            if consecutive == 0 {
+                first_bonus = bonus;
+            } else {
+                if bonus >= BONUS_BOUNDARY && bonus > first_bonus {
+                    first_bonus = bonus;
+                }
+                bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            score += if pidx == 0 {
+                bonus * BONUS_FIRST_CHAR_MULTIPLIER
+            } else {
+                bonus
+            };
+            in_gap = false;
+            consecutive += 1;
+            pidx += 1;
+        } else {
+            score += if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            in_gap = true;
+            consecutive = 0;
+            first_bonus = 0;
+        }
+        prev_class = class;
+    }
+
+    Some(Match { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_only() {
+        assert!(fuzzy_match("abc", "a_b_c").is_some());
+        assert!(fuzzy_match("abc", "acb").is_none());
+        assert_eq!(fuzzy_match("", "anything").unwrap().score, 0);
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(fuzzy_match("fb", "Foo/Bar").is_some());
+        assert!(fuzzy_match("Fb", "foo/bar").is_none());
+        assert!(fuzzy_match("Fb", "Foo/bar").is_some());
+    }
+
+    #[test]
+    fn reports_the_tightest_positions() {
+        assert_eq!(fuzzy_match("fb", "foo/bar").unwrap().positions, [0, 4]);
+        assert_eq!(fuzzy_match("ab", "a_ab").unwrap().positions, [2, 3]);
+    }
+
+    #[test]
+    fn scores_boundaries_and_runs() {
+        assert_eq!(fuzzy_match("abc", "abc").unwrap().score, 88);
+        let score = |pattern, text| fuzzy_match(pattern, text).unwrap().score;
+        assert!(score("abc", "abc") > score("abc", "a_b_c"));
+        assert!(score("bar", "foo/bar") > score("bar", "foobar"));
+        assert!(score("fb", "fooBar") > score("fb", "foobar"));
+    }
+}
@@ -0,0 +1,210 @@
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{paths, store};
+
+const HOUR: u64 = 60 * 60;
+pub const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MAX_TOTAL_RANK: f64 = 10000.0;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub rank: f64,
+    pub last_access: u64,
+}
+
+impl Entry {
+    pub fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_access);
+        let factor = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        self.rank * factor
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn history_file() -> io::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("history"))
+}
+
+pub fn load() -> io::Result<Vec<Entry>> {
+    let file = history_file()?;
+    let lock = store::lock_file(&file)?;
+    lock.lock_shared()?;
+    read(&file)
+}
+
+pub fn ranked() -> io::Result<Vec<Entry>> {
+    let now = now();
+    let mut entries = load()?;
+    entries.sort_by(|a, b| {
+        b.frecency(now)
+            .total_cmp(&a.frecency(now))
+            .then(b.last_access.cmp(&a.last_access))
+    });
+    Ok(entries)
+}
+
+pub fn update<T>(f: impl FnOnce(&mut Vec<Entry>) -> T) -> io::Result<T> {
+    let file = history_file()?;
+    let lock = store::lock_file(&file)?;
+    lock.lock()?;
+    let mut entries = read(&file)?;
+    let result = f(&mut entries);
+    write(&file, &entries)?;
+    Ok(result)
+}
+
+pub fn record(path: &Path) -> io::Result<()> {
+    update(|entries| visit(entries, path, now()))
+}
+
+fn visit(entries: &mut Vec<Entry>, path: &Path, now: u64) {
+    match entries.iter_mut().find(|e| e.path == path) {
+        Some(entry) => {
+            entry.rank += 1.0;
+            entry.last_access = now;
+        }
+        None => entries.push(Entry {
+            path: path.to_path_buf(),
+            rank: 1.0,
+            last_access: now,
+        }),
+    }
+
+    let total: f64 = entries.iter().map(|e| e.rank).sum();
+    if total > MAX_TOTAL_RANK {
+        for entry in entries.iter_mut() {
+            entry.rank *= 0.9;
+        }
+        entries.retain(|e| e.rank >= 1.0);
+    }
+}
+
+fn read(file: &Path) -> io::Result<Vec<Entry>> {
+    Ok(parse(&store::read(file)?))
+}
+
+fn write(file: &Path, entries: &[Entry]) -> io::Result<()> {
+    store::write_atomic(file, &serialize(entries))
+}
+
+fn parse(data: &[u8]) -> Vec<Entry> {
+    data.split(|&b| b == b'\0')
+        .filter_map(|record| {
+            let mut fields = record.splitn(3, |&b| b == b'\t');
+            let rank = std::str::from_utf8(fields.next()?).ok()?.parse().ok()?;
+            let last_access = std::str::from_utf8(fields.next()?).ok()?.parse().ok()?;
+            let path = fields.next().filter(|p| !p.is_empty())?;
+            Some(Entry {
+                path: PathBuf::from(OsStr::from_bytes(path)),
+                rank,
+                last_access,
+            })
+        })
+        .collect()
+}
+
+fn serialize(entries: &[Entry]) -> Vec<u8> {
+    let mut data = vec![];
+    for entry in entries {
+        data.extend(format!("{}\t{}\t", entry.rank, entry.last_access).bytes());
+        data.extend_from_slice(entry.path.as_os_str().as_bytes());
+        data.push(b'\0');
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &[u8], rank: f64, last_access: u64) -> Entry {
+        Entry {
+            path: PathBuf::from(OsStr::from_bytes(path)),
+            rank,
+            last_access,
+        }
+    }
+
+    fn summary(entries: &[Entry]) -> Vec<(&[u8], f64, u64)> {
+        entries
+            .iter()
+            .map(|e| (e.path.as_os_str().as_bytes(), e.rank, e.last_access))
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_awkward_paths() {
+        let entries = [
+            entry(b"/plain", 1.0, 10),
+            entry(b"/tab\there", 2.5, 20),
+            entry(b"/new\nline", 0.25, 30),
+            entry(b"/bytes\xff\xfe", 3.0, 40),
+        ];
+        let data = serialize(&entries);
+        assert_eq!(summary(&parse(&data)), summary(&entries));
+    }
+
+    #[test]
+    fn skips_malformed_records() {
+        let data = b"1\t10\t/ok\0garbage\0x\t10\t/bad-rank\x001\ty\t/bad-time\x001\t10\t\0\0";
+        assert_eq!(summary(&parse(data)), [(&b"/ok"[..], 1.0, 10)]);
+        assert!(parse(b"").is_empty());
+    }
+
+    #[test]
+    fn visit_bumps_existing_entries() {
+        let mut entries = vec![entry(b"/a", 2.0, 10)];
+        visit(&mut entries, Path::new("/a"), 50);
+        visit(&mut entries, Path::new("/b"), 60);
+        assert_eq!(
+            summary(&entries),
+            [(&b"/a"[..], 3.0, 50), (&b"/b"[..], 1.0, 60)]
+        );
+    }
+
+    #[test]
+    fn visit_ages_ranks_past_the_limit() {
+        let mut entries = vec![
+            entry(b"/busy", MAX_TOTAL_RANK - 1.0, 10),
+            entry(b"/rare", 1.0, 10),
+        ];
+        visit(&mut entries, Path::new("/busy"), 20);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, Path::new("/busy"));
+        assert_eq!(entries[0].rank, MAX_TOTAL_RANK * 0.9);
+
+        let mut entries = vec![entry(b"/a", 100.0, 10)];
+        visit(&mut entries, Path::new("/a"), 20);
+        assert_eq!(entries[0].rank, 101.0);
+    }
+
+    #[test]
+    fn frecency_favors_recent_visits() {
+        let now = 10 * WEEK;
+        let score = |age| entry(b"/a", 2.0, now - age).frecency(now);
+        assert_eq!(score(0), 8.0);
+        assert_eq!(score(2 * HOUR), 4.0);
+        assert_eq!(score(2 * DAY), 1.0);
+        assert_eq!(score(2 * WEEK), 0.5);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Up,
+    Down,
+    Expand,
+    Collapse,
+    Select,
+    Quit,
+    ToggleFiles,
+    ToggleHidden,
+    ToggleIgnored,
+    Sort,
+    Refresh,
+    FollowLink,
+    Filter,
+    NextMatch,
+    PrevMatch,
+    Cancel,
+    Search,
+    AddBookmark,
+    Bookmarks,
+}
+
+impl Action {
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Up => &["Up", "Ctrl-k"],
+            Action::Down => &["Down", "Ctrl-j"],
+            Action::Expand => &["Right", "Ctrl-l"],
+            Action::Collapse => &["Left", "Ctrl-h"],
+            Action::Select => &["Enter"],
+            Action::Quit => &["Ctrl-q"],
+            Action::ToggleFiles => &["Ctrl-f"],
+            Action::ToggleHidden => &["Ctrl-t"],
+            Action::ToggleIgnored => &["Alt-i"],
+            Action::Sort => &["Alt-s"],
+            Action::Refresh => &["Ctrl-r", "F5"],
+            Action::FollowLink => &["Alt-g"],
+            Action::Filter => &["/"],
+            Action::NextMatch => &["n"],
+            Action::PrevMatch => &["N"],
+            Action::Cancel => &["Esc"],
+            Action::Search => &["Ctrl-s"],
+            Action::AddBookmark => &["Ctrl-b"],
+            Action::Bookmarks => &["Ctrl-o"],
+        }
+    }
+
+    const ALL: [Action; 19] = [
+        Action::Up,
+        Action::Down,
+        Action::Expand,
+        Action::Collapse,
+        Action::Select,
+        Action::Quit,
+        Action::ToggleFiles,
+        Action::ToggleHidden,
+        Action::ToggleIgnored,
+        Action::Sort,
+        Action::Refresh,
+        Action::FollowLink,
+        Action::Filter,
+        Action::NextMatch,
+        Action::PrevMatch,
+        Action::Cancel,
+        Action::Search,
+        Action::AddBookmark,
+        Action::Bookmarks,
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Expand => "expand",
+            Action::Collapse => "collapse",
+            Action::Select => "select",
+            Action::Quit => "quit",
+            Action::ToggleFiles => "toggle_files",
+            Action::ToggleHidden => "toggle_hidden",
+            Action::ToggleIgnored => "toggle_ignored",
+            Action::Sort => "sort",
+            Action::Refresh => "refresh",
+            Action::FollowLink => "follow_link",
+            Action::Filter => "filter",
+            Action::NextMatch => "next_match",
+            Action::PrevMatch => "prev_match",
+            Action::Cancel => "cancel",
+            Action::Search => "search",
+            Action::AddBookmark => "add_bookmark",
+            Action::Bookmarks => "bookmarks",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn from_event(key: &KeyEvent) -> Self {
+        let mut modifiers =
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        let code = match key.code {
+            KeyCode::BackTab => KeyCode::Tab,
+            code => code,
+        };
+        if key.code == KeyCode::BackTab {
+            modifiers.insert(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        while let Some(i) = rest
+            .find(['-', '+'])
+            .filter(|&i| i > 0 && i + 1 < rest.len())
+        {
+            let modifier = match rest[..i].to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier {:?} in key {:?}", &rest[..i], s)),
+            };
+            modifiers.insert(modifier);
+            rest = &rest[i + 1..];
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => {
+                if modifiers.contains(KeyModifiers::SHIFT) {
+                    modifiers.remove(KeyModifiers::SHIFT);
+                    KeyCode::Char(c.to_ascii_uppercase())
+                } else if modifiers.contains(KeyModifiers::CONTROL) {
+                    KeyCode::Char(c.to_ascii_lowercase())
+                } else {
+                    KeyCode::Char(c)
+                }
+            }
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "space" => KeyCode::Char(' '),
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key {:?}", s)),
+                },
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyChord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyChord, Action>,
+    keys: HashMap<Action, Vec<KeyChord>>,
+}
+
+impl Keymap {
+    pub fn new(overrides: &HashMap<Action, Vec<KeyChord>>) -> Result<Self, String> {
+        let mut bindings = HashMap::new();
+        let mut keys_by_action = HashMap::new();
+        for action in Action::ALL {
+            let keys = match overrides.get(&action) {
+                Some(keys) => keys.clone(),
+                None => action
+                    .default_keys()
+                    .iter()
+                    .map(|k| k.parse().expect("default key bindings are valid"))
+                    .collect(),
+            };
+            keys_by_action.insert(action, keys.clone());
+            for key in keys {
+                if let Some(other) = bindings.insert(key, action) {
+                    return Err(format!(
+                        "key {} is bound to both {} and {}",
+                        key, other, action
+                    ));
+                }
+            }
+        }
+        Ok(Self {
+            bindings,
+            keys: keys_by_action,
+        })
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings.get(&KeyChord::from_event(key)).copied()
+    }
+
+    pub fn key(&self, action: Action) -> Option<KeyChord> {
+        self.keys.get(&action)?.first().copied()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&HashMap::new()).expect("default key bindings do not conflict")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        KeyChord::from_event(&KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn parses_modifiers_and_keys() {
+        assert_eq!(
+            chord("Ctrl-a"),
+            event(KeyCode::Char('a'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            chord("C-A"),
+            event(KeyCode::Char('a'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            chord("ctrl+alt+x"),
+            event(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(chord("F5"), event(KeyCode::F(5), KeyModifiers::NONE));
+        assert_eq!(
+            chord("space"),
+            event(KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn dash_can_be_the_key() {
+        assert_eq!(chord("-"), event(KeyCode::Char('-'), KeyModifiers::NONE));
+        assert_eq!(
+            chord("Ctrl--"),
+            event(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(chord("Alt-+"), event(KeyCode::Char('+'), KeyModifiers::ALT));
+    }
+
+    #[test]
+    fn shift_matches_what_the_terminal_reports() {
+        assert_eq!(
+            chord("Shift-Tab"),
+            event(KeyCode::BackTab, KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            chord("Shift-a"),
+            event(KeyCode::Char('A'), KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            chord("Alt-S"),
+            event(KeyCode::Char('S'), KeyModifiers::ALT | KeyModifiers::SHIFT)
+        );
+        assert_ne!(chord("Alt-S"), chord("Alt-s"));
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!("Hyper-x".parse::<KeyChord>().is_err());
+        assert!("Ctrl-foo".parse::<KeyChord>().is_err());
+        assert!("F13".parse::<KeyChord>().is_err());
+    }
+}
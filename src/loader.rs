@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::tree::{DirEntry, LoadError, NodeId};
+use crate::visibility::Visibility;
+
+const BATCH_SIZE: usize = 256;
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+pub enum LoadUpdate {
+    Entries(NodeId, Vec<DirEntry>),
+    Done(NodeId, HashSet<PathBuf>),
+    Failed(NodeId, LoadError),
+    TimedOut(NodeId),
+}
+
+struct Message {
+    id: NodeId,
+    ticket: u64,
+    entries: Vec<DirEntry>,
+    done: bool,
+    error: Option<io::Error>,
+}
+
+struct Pending {
+    ticket: u64,
+    started: Instant,
+    cancel: Arc<AtomicBool>,
+    known: HashSet<PathBuf>,
+    seen: HashSet<PathBuf>,
+}
+
+pub struct Loader {
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+    pending: HashMap<NodeId, Pending>,
+    next_ticket: u64,
+    timeout: Option<Duration>,
+}
+
+impl Loader {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            tx,
+            rx,
+            pending: HashMap::new(),
+            next_ticket: 0,
+            timeout,
+        }
+    }
+
+    pub fn is_loading(&self, id: NodeId) -> bool {
+        self.pending.contains_key(&id)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn start(
+        &mut self,
+        id: NodeId,
+        dir: PathBuf,
+        known: HashSet<PathBuf>,
+        visibility: Visibility,
+    ) {
+        if let Some(previous) = self.pending.remove(&id) {
+            previous.cancel.store(true, Ordering::Relaxed);
+        }
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.pending.insert(
+            id,
+            Pending {
+                ticket,
+                started: Instant::now(),
+                cancel: cancel.clone(),
+                known,
+                seen: HashSet::new(),
+            },
+        );
+
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let mut entries = vec![];
+            let mut last_sent = Instant::now();
+            let result = visibility.for_each_subdir(&dir, |path, file_type| {
+                if cancel.load(Ordering::Relaxed) {
+                    return false;
+                }
+                entries.push(DirEntry::listed(path, file_type));
+                if entries.len() >= BATCH_SIZE || last_sent.elapsed() >= BATCH_INTERVAL {
+                    last_sent = Instant::now();
+                    let message = Message {
+                        id,
+                        ticket,
+                        entries: std::mem::take(&mut entries),
+                        done: false,
+                        error: None,
+                    };
+                    return tx.send(message).is_ok();
+                }
+                true
+            });
+            if !cancel.load(Ordering::Relaxed) {
+                let _ = tx.send(Message {
+                    id,
+                    ticket,
+                    entries,
+                    done: true,
+                    error: result.err(),
+                });
+            }
+        });
+    }
+
+    pub fn cancel_all(&mut self) -> Vec<NodeId> {
+        self.pending
+            .drain()
+            .map(|(id, pending)| {
+                pending.cancel.store(true, Ordering::Relaxed);
+                id
+            })
+            .collect()
+    }
+
+    pub fn poll(&mut self) -> Vec<LoadUpdate> {
+        let mut updates = vec![];
+        for mut message in self.rx.try_iter() {
+            let Some(pending) = self
+                .pending
+                .get_mut(&message.id)
+                .filter(|p| p.ticket == message.ticket)
+            else {
+                continue;
+            };
+            message.entries.retain(|e| {
+                pending.seen.insert(e.path.clone()) && !pending.known.contains(&e.path)
+            });
+            if !message.entries.is_empty() {
+                updates.push(LoadUpdate::Entries(message.id, message.entries));
+            }
+            if message.done
+                && let Some(pending) = self.pending.remove(&message.id)
+            {
+                updates.push(match message.error {
+                    Some(e) => LoadUpdate::Failed(message.id, e.into()),
+                    None => LoadUpdate::Done(message.id, pending.seen),
+                });
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            let expired: Vec<NodeId> = self
+                .pending
+                .iter()
+                .filter(|(_, p)| p.started.elapsed() >= timeout)
+                .map(|(&id, _)| id)
+                .collect();
+            for id in expired {
+                if let Some(pending) = self.pending.remove(&id) {
+                    pending.cancel.store(true, Ordering::Relaxed);
+                }
+                updates.push(LoadUpdate::TimedOut(id));
+            }
+        }
+        updates
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.cancel_all();
+    }
+}
@@ -1,9 +1,292 @@
 mod app;
+mod bookmarks;
+mod cli;
+mod config;
+mod fuzzy;
+mod history;
+mod keymap;
+mod loader;
+mod output;
+mod paths;
+mod search;
+mod shell;
+mod sort;
+mod store;
+mod terminal;
 mod tree;
+mod visibility;
+mod watcher;
 
-use std::io;
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
 
-fn main() -> io::Result<()> {
-    let mut app = app::App::new()?;
-    app.run()
+use clap::{CommandFactory, Parser, error::ErrorKind};
+
+use crate::app::Exit;
+use crate::cli::{BookmarkCommand, Cli, Command, ConfigCommand, HistoryCommand};
+use crate::config::Config;
+use crate::output::Output;
+use crate::shell::Shell;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("cda: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run() -> io::Result<ExitCode> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Init { shell, exec }) => {
+            print!("{}", shell::init::script(shell, exec));
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Bookmark { command }) => {
+            bookmark_command(command.unwrap_or(BookmarkCommand::Ls))?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::History { command }) => {
+            history_command(command.unwrap_or(HistoryCommand::List { scores: false }))?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Config { command }) => {
+            config_command(command.unwrap_or(ConfigCommand::Check))?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
+
+    let mut status = None;
+    let start = match &cli.path {
+        Some(path) => resolve_dir(path).unwrap_or_else(|e| {
+            usage_error(format!("invalid start directory {}: {}", path.display(), e))
+        }),
+        None => paths::current_dir().unwrap_or_else(|e| {
+            let (dir, message) = current_dir_fallback(e);
+            status = Some(message);
+            dir
+        }),
+    };
+    let top = match &cli.root {
+        Some(path) => resolve_dir(path).unwrap_or_else(|e| {
+            usage_error(format!("invalid root directory {}: {}", path.display(), e))
+        }),
+        None => PathBuf::from("/"),
+    };
+    if !start.starts_with(&top) {
+        usage_error(format!(
+            "{} is not inside the root {}",
+            start.display(),
+            top.display()
+        ));
+    }
+
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("cda: {}", e);
+        process::exit(2)
+    });
+
+    let mut app = if cli.list {
+        if io::stdin().is_terminal() {
+            usage_error("--list reads the paths from stdin".to_string());
+        }
+        app::App::from_list(read_paths(cli.read0)?, cli.stat, config)
+    } else if io::stdin().is_terminal() {
+        app::App::new(&start, &top, vec![], config)?
+    } else {
+        let mut seen = HashSet::new();
+        let candidates = read_paths(cli.read0)?
+            .into_iter()
+            .map(|path| start.join(path))
+            .filter(|path| path.is_dir() && seen.insert(path.clone()))
+            .collect();
+        app::App::new(&start, &top, candidates, config)?
+    };
+    if let Some(status) = status {
+        app.set_status(status);
+    }
+    let exit = app.run(cli.height)?;
+    if let Exit::Select(path) = &exit
+        && !cli.list
+        && let Err(e) = history::record(path)
+    {
+        eprintln!("cda: failed to update history: {}", e);
+    }
+
+    let cwd = paths::current_dir().unwrap_or_else(|_| start.clone());
+    let shell = cli.shell.unwrap_or_else(Shell::detect);
+    let output = match (cli.print_path, cli.output) {
+        (true, _) => Output::Path,
+        (false, output) => output.unwrap_or(Output::Cd),
+    };
+    let mut out = match &exit {
+        _ if output == Output::Json => {
+            output::json(&exit, &cwd, !cli.list || cli.stat)?.into_bytes()
+        }
+        Exit::Cancel | Exit::Interrupt => return Ok(exit_code(&exit)),
+        Exit::Select(path) => match (&cli.format, output) {
+            (Some(template), _) => template.render(path, &cwd, shell)?,
+            (None, Output::Path) => path.as_os_str().as_bytes().to_vec(),
+            (None, _) => shell.cd_command(path)?,
+        },
+    };
+    out.push(if cli.null { b'\0' } else { b'\n' });
+    io::stdout().write_all(&out)?;
+    Ok(exit_code(&exit))
+}
+
+fn exit_code(exit: &Exit) -> ExitCode {
+    match exit {
+        Exit::Select(_) => ExitCode::SUCCESS,
+        Exit::Cancel => ExitCode::from(1),
+        Exit::Interrupt => ExitCode::from(130),
+    }
+}
+
+fn bookmark_command(command: BookmarkCommand) -> io::Result<()> {
+    match command {
+        BookmarkCommand::Add { name, path } => {
+            let path = match path {
+                Some(path) => resolve_dir(&path).unwrap_or_else(|e| {
+                    usage_error(format!("invalid directory {}: {}", path.display(), e))
+                }),
+                None => paths::current_dir()?,
+            };
+            let name = name.unwrap_or_else(|| {
+                path.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string())
+            });
+            if let Err(e) = bookmarks::validate_name(&name) {
+                usage_error(e.to_string());
+            }
+            bookmarks::add(&name, &path)?;
+        }
+        BookmarkCommand::Rm { name } => {
+            if !bookmarks::remove(&name)? {
+                usage_error(format!("no bookmark named {:?}", name));
+            }
+        }
+        BookmarkCommand::Ls => {
+            let mut stdout = io::stdout().lock();
+            for bookmark in bookmarks::load()? {
+                write!(stdout, "{}\t", bookmark.name)?;
+                stdout.write_all(bookmark.path.as_os_str().as_bytes())?;
+                stdout.write_all(b"\n")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn history_command(command: HistoryCommand) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match command {
+        HistoryCommand::List { scores } => {
+            let now = history::now();
+            for entry in history::ranked()? {
+                if scores {
+                    write!(stdout, "{:>10.1}  ", entry.frecency(now))?;
+                }
+                stdout.write_all(entry.path.as_os_str().as_bytes())?;
+                stdout.write_all(b"\n")?;
+            }
+        }
+        HistoryCommand::Prune { older_than } => {
+            let cutoff = older_than
+                .map(|days| history::now().saturating_sub(days.saturating_mul(history::DAY)));
+            let removed = history::update(|entries| {
+                let before = entries.len();
+                entries.retain(|e| e.path.is_dir() && cutoff.is_none_or(|c| e.last_access >= c));
+                before - entries.len()
+            })?;
+            eprintln!("removed {} entries", removed);
+        }
+        HistoryCommand::Export => {
+            for entry in history::load()? {
+                stdout.write_all(entry.path.as_os_str().as_bytes())?;
+                writeln!(stdout, "|{}|{}", entry.rank, entry.last_access)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn config_command(command: ConfigCommand) -> io::Result<()> {
+    match command {
+        ConfigCommand::Check => match Config::load() {
+            Ok(_) => println!("{}: ok", Config::path()?.display()),
+            Err(e) => {
+                eprintln!("cda: {}", e);
+                process::exit(2);
+            }
+        },
+        ConfigCommand::Path => println!("{}", Config::path()?.display()),
+    }
+    Ok(())
+}
+
+fn resolve_dir(path: &Path) -> io::Result<PathBuf> {
+    let path = paths::logical(path)?;
+    if !path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} is not a directory", path.display()),
+        ));
+    }
+    Ok(path)
+}
+
+fn read_paths(read0: bool) -> io::Result<Vec<PathBuf>> {
+    let mut input = vec![];
+    io::stdin().read_to_end(&mut input)?;
+    let separator = if read0 { b'\0' } else { b'\n' };
+    Ok(input
+        .split(|&b| b == separator)
+        .map(|line| {
+            if read0 {
+                line
+            } else {
+                line.strip_suffix(b"\r").unwrap_or(line)
+            }
+        })
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(OsStr::from_bytes(line)))
+        .collect())
+}
+
+fn current_dir_fallback(error: io::Error) -> (PathBuf, String) {
+    let pwd = env::var_os("PWD").map(PathBuf::from).unwrap_or_default();
+    let dir = paths::nearest_existing(&pwd).unwrap_or_else(|| PathBuf::from("/"));
+    let what = if pwd.as_os_str().is_empty() {
+        "the current directory".to_string()
+    } else {
+        pwd.display().to_string()
+    };
+    let message = match error.kind() {
+        io::ErrorKind::NotFound => format!("{} no longer exists, showing {}", what, dir.display()),
+        _ => format!(
+            "cannot access {} ({}), showing {}",
+            what,
+            error,
+            dir.display()
+        ),
+    };
+    (dir, message)
+}
+
+fn usage_error(message: String) -> ! {
+    Cli::command()
+        .error(ErrorKind::ValueValidation, message)
+        .exit()
 }
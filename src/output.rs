@@ -0,0 +1,223 @@
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::app::Exit;
+use crate::paths;
+use crate::shell::Shell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Output {
+    Cd,
+    Path,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Path,
+    Relpath,
+    Name,
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Field { field: Field, quoted: bool },
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = vec![];
+        let mut text = String::new();
+        let mut rest = s;
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = rest.strip_prefix("{{").or_else(|| rest.strip_prefix("}}")) {
+                text.push(c);
+                rest = after;
+                continue;
+            }
+            if c != '{' {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let end = rest
+                .find('}')
+                .ok_or_else(|| format!("unclosed {{ in format {:?}", s))?;
+            let name = &rest[1..end];
+            let (quoted, field) = match name.strip_prefix("q:") {
+                Some(field) => (true, field),
+                None => (false, name),
+            };
+            let field = match field {
+                "path" => Field::Path,
+                "relpath" => Field::Relpath,
+                "name" => Field::Name,
+                _ => return Err(format!("unknown field {{{}}} in format {:?}", name, s)),
+            };
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+            pieces.push(Piece::Field { field, quoted });
+            rest = &rest[end + 1..];
+        }
+        pieces.push(Piece::Text(text));
+        Ok(Self { pieces })
+    }
+}
+
+impl Template {
+    pub fn render(&self, path: &Path, cwd: &Path, shell: Shell) -> io::Result<Vec<u8>> {
+        let mut out = vec![];
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => out.extend_from_slice(text.as_bytes()),
+                Piece::Field { field, quoted } => {
+                    let value = match field {
+                        Field::Path => path.to_path_buf(),
+                        Field::Relpath => paths::relative(path, cwd),
+                        Field::Name => name(path),
+                    };
+                    if *quoted {
+                        out.extend(shell.quote(value.as_os_str())?);
+                    } else {
+                        out.extend_from_slice(value.as_os_str().as_bytes());
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    reason: &'static str,
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_bytes: Option<Vec<u8>>,
+    symlink: bool,
+    link_target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link_target_bytes: Option<Vec<u8>>,
+    cwd: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd_bytes: Option<Vec<u8>>,
+}
+
+pub fn json(exit: &Exit, cwd: &Path, inspect: bool) -> io::Result<String> {
+    let (reason, path) = match exit {
+        Exit::Select(path) => ("select", Some(path.as_path())),
+        Exit::Cancel => ("cancel", None),
+        Exit::Interrupt => ("interrupt", None),
+    };
+    let link_target = path.filter(|_| inspect).and_then(|path| {
+        let meta = fs::symlink_metadata(path).ok()?;
+        meta.file_type()
+            .is_symlink()
+            .then(|| path.read_link().ok())?
+    });
+
+    let report = Report {
+        reason,
+        path: path.map(lossy),
+        path_bytes: path.and_then(non_utf8_bytes),
+        symlink: link_target.is_some(),
+        link_target: link_target.as_deref().map(lossy),
+        link_target_bytes: link_target.as_deref().and_then(non_utf8_bytes),
+        cwd: lossy(cwd),
+        cwd_bytes: non_utf8_bytes(cwd),
+    };
+    Ok(serde_json::to_string(&report)?)
+}
+
+fn name(path: &Path) -> PathBuf {
+    path.file_name()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn lossy(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn non_utf8_bytes(path: &Path) -> Option<Vec<u8>> {
+    path.to_str()
+        .is_none()
+        .then(|| path.as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, path: &str) -> String {
+        let template: Template = template.parse().unwrap();
+        let out = template
+            .render(Path::new(path), Path::new("/home/me"), Shell::Bash)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn expands_fields() {
+        assert_eq!(render("{path}", "/home/me/src/app"), "/home/me/src/app");
+        assert_eq!(render("{relpath}", "/home/me/src/app"), "src/app");
+        assert_eq!(render("{relpath}", "/home/you"), "../you");
+        assert_eq!(render("{relpath}", "/home/me"), ".");
+        assert_eq!(render("{name}", "/home/me/src/app"), "app");
+        assert_eq!(
+            render("cd {q:path} # {name}", "/tmp/a b"),
+            "cd '/tmp/a b' # a b"
+        );
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{path}}", "/tmp"), "{path}");
+        assert_eq!(render("{{{name}}}", "/tmp"), "{tmp}");
+        assert_eq!(render("a}b", "/tmp"), "a}b");
+    }
+
+    #[test]
+    fn rejects_bad_fields() {
+        assert!("{nope}".parse::<Template>().is_err());
+        assert!("{q:nope}".parse::<Template>().is_err());
+        assert!("{q:}".parse::<Template>().is_err());
+        assert!("{path".parse::<Template>().is_err());
+        assert!("cd {".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn json_reports_the_selection() {
+        let exit = Exit::Select(PathBuf::from("/tmp/a \"b\""));
+        assert_eq!(
+            json(&exit, Path::new("/tmp"), false).unwrap(),
+            r#"{"reason":"select","path":"/tmp/a \"b\"","symlink":false,"link_target":null,"cwd":"/tmp"}"#
+        );
+        assert_eq!(
+            json(&Exit::Cancel, Path::new("/tmp"), true).unwrap(),
+            r#"{"reason":"cancel","path":null,"symlink":false,"link_target":null,"cwd":"/tmp"}"#
+        );
+    }
+
+    #[test]
+    fn json_keeps_non_utf8_paths_as_bytes() {
+        let path = PathBuf::from(std::ffi::OsStr::from_bytes(b"/t\xff"));
+        assert_eq!(
+            json(&Exit::Select(path), Path::new("/"), false).unwrap(),
+            "{\"reason\":\"select\",\"path\":\"/t\u{fffd}\",\"path_bytes\":[47,116,255],\
+             \"symlink\":false,\"link_target\":null,\"cwd\":\"/\"}"
+        );
+    }
+}
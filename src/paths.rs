@@ -0,0 +1,104 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+
+fn xdg_dir(var: &str, fallback: &str) -> io::Result<PathBuf> {
+    if let Some(dir) = env::var_os(var).map(PathBuf::from)
+        && dir.is_absolute()
+    {
+        return Ok(dir.join("cda"));
+    }
+    match env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(fallback).join("cda")),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("neither ${} nor $HOME is set", var),
+        )),
+    }
+}
+
+pub fn data_dir() -> io::Result<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+pub fn config_dir() -> io::Result<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn current_dir() -> io::Result<PathBuf> {
+    let physical = env::current_dir()?;
+    if let Some(pwd) = env::var_os("PWD").map(PathBuf::from)
+        && pwd.is_absolute()
+        && normalize(&pwd) == pwd
+        && same_file(&pwd, &physical)
+    {
+        return Ok(pwd);
+    }
+    Ok(physical)
+}
+
+pub fn nearest_existing(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|p| p.is_absolute() && p.is_dir())
+        .map(Path::to_path_buf)
+}
+
+pub fn logical(path: &Path) -> io::Result<PathBuf> {
+    let physical = path.canonicalize()?;
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        current_dir()?.join(path)
+    };
+    let logical = normalize(&absolute);
+    if same_file(&logical, &physical) {
+        Ok(logical)
+    } else {
+        Ok(physical)
+    }
+}
+
+pub fn relative(path: &Path, base: &Path) -> PathBuf {
+    if path.is_relative() {
+        return path.to_path_buf();
+    }
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    for component in path.components().skip(common) {
+        relative.push(component);
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::fuzzy::{Match, fuzzy_match};
+use crate::visibility::Visibility;
+
+pub enum SearchEvent {
+    Found(PathBuf, String, Match),
+    Progress(usize),
+    Done(usize),
+}
+
+pub struct Search {
+    rx: Receiver<SearchEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Search {
+    pub fn spawn(root: PathBuf, query: String, max_depth: usize, visibility: Visibility) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelled = cancel.clone();
+
+        thread::spawn(move || {
+            let walker = visibility.walker(&root).max_depth(Some(max_depth)).build();
+
+            let mut scanned = 0;
+            for entry in walker {
+                if cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Ok(entry) = entry else {
+                    continue;
+                };
+                if entry.depth() == 0 {
+                    continue;
+                }
+                let is_dir = match entry.file_type() {
+                    Some(ft) if ft.is_dir() => true,
+                    Some(ft) if ft.is_symlink() => entry.path().is_dir(),
+                    _ => false,
+                };
+                if !is_dir {
+                    continue;
+                }
+
+                scanned += 1;
+                let path = entry.into_path();
+                let relative = path
+                    .strip_prefix(&root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string();
+                let event = match fuzzy_match(&query, &relative) {
+                    Some(m) => Some(SearchEvent::Found(path, relative, m)),
+                    None if scanned % 256 == 0 => Some(SearchEvent::Progress(scanned)),
+                    None => None,
+                };
+                if let Some(event) = event
+                    && tx.send(event).is_err()
+                {
+                    return;
+                }
+            }
+            let _ = tx.send(SearchEvent::Done(scanned));
+        });
+
+        Self { rx, cancel }
+    }
+
+    pub fn events(&self) -> mpsc::TryIter<'_, SearchEvent> {
+        self.rx.try_iter()
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,272 @@
+pub mod init;
+
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::{env, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "nu")]
+    Nushell,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = Path::new(name)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(name)
+            .trim_start_matches('-');
+        match name {
+            "bash" | "sh" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "nu" | "nushell" => Some(Shell::Nushell),
+            _ => None,
+        }
+    }
+
+    pub fn detect() -> Self {
+        ["CDA_SHELL", "SHELL"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find_map(|name| Self::from_name(&name))
+            .unwrap_or(Shell::Bash)
+    }
+
+    pub fn cd_command(self, path: &Path) -> io::Result<Vec<u8>> {
+        let arg = path.as_os_str();
+        let mut out = b"cd ".to_vec();
+        if arg.as_bytes().starts_with(b"-") && self != Shell::Nushell {
+            out.extend_from_slice(b"-- ");
+        }
+        out.extend(self.quote(arg)?);
+        Ok(out)
+    }
+
+    pub fn quote(self, arg: &OsStr) -> io::Result<Vec<u8>> {
+        let bytes = arg.as_bytes();
+        if is_bare(bytes) {
+            return Ok(bytes.to_vec());
+        }
+        match self {
+            Shell::Bash | Shell::Zsh => Ok(quote_posix(bytes)),
+            Shell::Fish => Ok(quote_fish(bytes)),
+            Shell::Nushell => quote_nushell(bytes),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Nushell => "nushell",
+        })
+    }
+}
+
+fn is_bare(bytes: &[u8]) -> bool {
+    let Some(&first) = bytes.first() else {
+        return false;
+    };
+    !matches!(first, b'-' | b'~' | b'=')
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || b"/._-+,:@".contains(b))
+}
+
+fn is_control(c: char) -> bool {
+    c.is_control() || c == '\u{2028}' || c == '\u{2029}'
+}
+
+fn quote_posix(bytes: &[u8]) -> Vec<u8> {
+    let needs_ansi_c = bytes
+        .utf8_chunks()
+        .any(|chunk| !chunk.invalid().is_empty() || chunk.valid().chars().any(is_control));
+
+    if !needs_ansi_c {
+        let mut out = vec![b'\''];
+        for &b in bytes {
+            if b == b'\'' {
+                out.extend_from_slice(b"'\\''");
+            } else {
+                out.push(b);
+            }
+        }
+        out.push(b'\'');
+        return out;
+    }
+
+    let mut out = b"$'".to_vec();
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => out.extend_from_slice(b"\\\\"),
+                '\'' => out.extend_from_slice(b"\\'"),
+                '\n' => out.extend_from_slice(b"\\n"),
+                '\t' => out.extend_from_slice(b"\\t"),
+                c if is_control(c) => {
+                    let mut buf = [0; 4];
+                    for b in c.encode_utf8(&mut buf).bytes() {
+                        out.extend(format!("\\x{:02x}", b).bytes());
+                    }
+                }
+                c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        for &b in chunk.invalid() {
+            out.extend(format!("\\x{:02x}", b).bytes());
+        }
+    }
+    out.push(b'\'');
+    out
+}
+
+fn quote_fish(bytes: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut in_quote = false;
+    let mut open = |out: &mut Vec<u8>, quoted: bool| {
+        if in_quote != quoted {
+            out.push(b'\'');
+            in_quote = quoted;
+        }
+    };
+
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if is_control(c) {
+                open(&mut out, false);
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    let escape = if b < 0x80 { 'x' } else { 'X' };
+                    out.extend(format!("\\{}{:02x}", escape, b).bytes());
+                }
+            } else {
+                open(&mut out, true);
+                match c {
+                    '\\' => out.extend_from_slice(b"\\\\"),
+                    '\'' => out.extend_from_slice(b"\\'"),
+                    c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                }
+            }
+        }
+        for &b in chunk.invalid() {
+            open(&mut out, false);
+            out.extend(format!("\\X{:02x}", b).bytes());
+        }
+    }
+    open(&mut out, false);
+    out
+}
+
+fn quote_nushell(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let s = std::str::from_utf8(bytes).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "path is not valid UTF-8 and cannot be quoted for nushell",
+        )
+    })?;
+
+    let mut hashes = "#".to_string();
+    while s.contains(&format!("'{}", hashes)) {
+        hashes.push('#');
+    }
+    Ok(format!("r{}'{}'{}", hashes, s, hashes).into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(shell: Shell, bytes: &[u8]) -> String {
+        String::from_utf8(shell.quote(OsStr::from_bytes(bytes)).unwrap()).unwrap()
+    }
+
+    fn cd(shell: Shell, bytes: &[u8]) -> String {
+        String::from_utf8(
+            shell
+                .cd_command(Path::new(OsStr::from_bytes(bytes)))
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn plain_paths_are_left_bare() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::Nushell] {
+            assert_eq!(
+                quote(shell, b"/tmp/src-1.0/a_b+c,d:e@f"),
+                "/tmp/src-1.0/a_b+c,d:e@f"
+            );
+        }
+    }
+
+    #[test]
+    fn posix_quoting() {
+        for shell in [Shell::Bash, Shell::Zsh] {
+            assert_eq!(quote(shell, b"/tmp/a b"), "'/tmp/a b'");
+            assert_eq!(quote(shell, b"/tmp/it's"), r"'/tmp/it'\''s'");
+            assert_eq!(quote(shell, b"/tmp/$HOME`id`"), "'/tmp/$HOME`id`'");
+            assert_eq!(quote(shell, b"/tmp/*.rs?[a]"), "'/tmp/*.rs?[a]'");
+            assert_eq!(quote(shell, b"~root"), "'~root'");
+            assert_eq!(quote(shell, b"=ls"), "'=ls'");
+            assert_eq!(quote(shell, b"/tmp/a\nb"), r"$'/tmp/a\nb'");
+            assert_eq!(quote(shell, b"it's\t\\"), r"$'it\'s\t\\'");
+            assert_eq!(
+                quote(shell, "/tmp/a\u{2028}b".as_bytes()),
+                r"$'/tmp/a\xe2\x80\xa8b'"
+            );
+            assert_eq!(quote(shell, b"/tmp/\xff\xfe"), r"$'/tmp/\xff\xfe'");
+        }
+    }
+
+    #[test]
+    fn fish_quoting() {
+        let shell = Shell::Fish;
+        assert_eq!(quote(shell, b"/tmp/a b"), "'/tmp/a b'");
+        assert_eq!(quote(shell, b"/tmp/it's"), r"'/tmp/it\'s'");
+        assert_eq!(quote(shell, b"/tmp/back\\slash"), r"'/tmp/back\\slash'");
+        assert_eq!(quote(shell, b"/tmp/$HOME(id)"), "'/tmp/$HOME(id)'");
+        assert_eq!(quote(shell, b"/tmp/*.rs?{a,b}"), "'/tmp/*.rs?{a,b}'");
+        assert_eq!(quote(shell, b"~root"), "'~root'");
+        assert_eq!(quote(shell, b"/tmp/a\nb"), r"'/tmp/a'\x0a'b'");
+        assert_eq!(
+            quote(shell, "/tmp/a\u{2028}b".as_bytes()),
+            r"'/tmp/a'\Xe2\X80\Xa8'b'"
+        );
+        assert_eq!(quote(shell, b"/tmp/\xff"), r"'/tmp/'\Xff");
+    }
+
+    #[test]
+    fn nushell_quoting() {
+        let shell = Shell::Nushell;
+        assert_eq!(quote(shell, b"/tmp/a b"), "r#'/tmp/a b'#");
+        assert_eq!(quote(shell, b"/tmp/it's $x *"), "r#'/tmp/it's $x *'#");
+        assert_eq!(quote(shell, b"/tmp/a'#b"), "r##'/tmp/a'#b'##");
+        assert_eq!(quote(shell, b"~root"), "r#'~root'#");
+        assert_eq!(quote(shell, b"/tmp/a\nb"), "r#'/tmp/a\nb'#");
+        assert_eq!(
+            quote(shell, "/tmp/a\u{2028}b".as_bytes()),
+            "r#'/tmp/a\u{2028}b'#"
+        );
+        let error = shell.quote(OsStr::from_bytes(b"/tmp/\xff")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn cd_ends_options_before_a_leading_dash() {
+        assert_eq!(cd(Shell::Bash, b"-rf"), "cd -- '-rf'");
+        assert_eq!(cd(Shell::Zsh, b"-"), "cd -- '-'");
+        assert_eq!(cd(Shell::Fish, b"-x y"), "cd -- '-x y'");
+        assert_eq!(cd(Shell::Nushell, b"-rf"), "cd r#'-rf'#");
+        assert_eq!(cd(Shell::Bash, b"/tmp"), "cd /tmp");
+    }
+}
@@ -0,0 +1,20 @@
+use crate::shell::Shell;
+
+const BASH: &str = include_str!("../../shell/cda.bash");
+const ZSH: &str = include_str!("../../shell/cda.zsh");
+const FISH: &str = include_str!("../../shell/cda.fish");
+const NUSHELL: &str = include_str!("../../shell/cda.nu");
+
+pub fn script(shell: Shell, exec: bool) -> String {
+    let (preamble, body) = match shell {
+        Shell::Bash => ("CDA_EXEC=1", BASH),
+        Shell::Zsh => ("CDA_EXEC=1", ZSH),
+        Shell::Fish => ("set -g CDA_EXEC 1", FISH),
+        Shell::Nushell => ("$env.CDA_EXEC = 1", NUSHELL),
+    };
+    if exec {
+        format!("{}\n\n{}", preamble, body)
+    } else {
+        body.to_string()
+    }
+}
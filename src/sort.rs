@@ -0,0 +1,218 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use crate::tree::{LinkStatus, NodeId, Tree};
+use crate::{history, paths, store};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    Name,
+    Natural,
+    IgnoreCase,
+    Modified,
+    Frecency,
+    SymlinksLast,
+}
+
+impl SortMode {
+    const ALL: [SortMode; 6] = [
+        SortMode::Name,
+        SortMode::Natural,
+        SortMode::IgnoreCase,
+        SortMode::Modified,
+        SortMode::Frecency,
+        SortMode::SymlinksLast,
+    ];
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn saved() -> Option<Self> {
+        let data = store::read(&state_file().ok()?).ok()?;
+        let name = String::from_utf8(data).ok()?;
+        Self::ALL.into_iter().find(|m| m.to_string() == name.trim())
+    }
+
+    pub fn save(self) -> io::Result<()> {
+        let file = state_file()?;
+        fs::create_dir_all(paths::data_dir()?)?;
+        store::write_atomic(&file, format!("{}\n", self).as_bytes())
+    }
+}
+
+fn state_file() -> io::Result<PathBuf> {
+    Ok(paths::data_dir()?.join("sort"))
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SortMode::Name => "name",
+            SortMode::Natural => "natural",
+            SortMode::IgnoreCase => "ignore_case",
+            SortMode::Modified => "modified",
+            SortMode::Frecency => "frecency",
+            SortMode::SymlinksLast => "symlinks_last",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sorter {
+    pub mode: SortMode,
+    frecency: HashMap<PathBuf, f64>,
+    stat: bool,
+}
+
+impl Sorter {
+    pub fn new(mode: SortMode, entries: &[history::Entry]) -> Self {
+        let now = history::now();
+        Self {
+            mode,
+            frecency: entries
+                .iter()
+                .map(|e| (e.path.clone(), e.frecency(now)))
+                .collect(),
+            stat: true,
+        }
+    }
+
+    pub fn without_stat(mode: SortMode) -> Self {
+        let mut sorter = Self::new(mode, &[]);
+        sorter.stat = false;
+        if mode == SortMode::Modified {
+            sorter.mode = SortMode::Natural;
+        }
+        sorter
+    }
+
+    pub fn cycle(&mut self) {
+        self.mode = self.mode.next();
+        if !self.stat && self.mode == SortMode::Modified {
+            self.mode = self.mode.next();
+        }
+    }
+
+    pub fn sort(&self, tree: &Tree, children: &mut [NodeId]) {
+        let name = |id: &NodeId| tree[*id].name.as_str();
+        match self.mode {
+            SortMode::Name => children.sort_by(|a, b| name(a).cmp(name(b))),
+            SortMode::Natural => children.sort_by(|a, b| natural_cmp(name(a), name(b))),
+            SortMode::IgnoreCase => children.sort_by(|a, b| {
+                let (a, b) = (name(a), name(b));
+                a.to_lowercase()
+                    .cmp(&b.to_lowercase())
+                    .then_with(|| a.cmp(b))
+            }),
+            SortMode::Modified => {
+                children.sort_by_cached_key(|c| {
+                    let modified = fs::metadata(&tree[*c].path).and_then(|m| m.modified()).ok();
+                    std::cmp::Reverse(modified.unwrap_or(SystemTime::UNIX_EPOCH))
+                });
+            }
+            SortMode::Frecency => children.sort_by(|a, b| {
+                let score = |c: &NodeId| self.frecency.get(&tree[*c].path).copied().unwrap_or(0.0);
+                score(b)
+                    .total_cmp(&score(a))
+                    .then_with(|| natural_cmp(name(a), name(b)))
+            }),
+            SortMode::SymlinksLast => children.sort_by(|a, b| {
+                let is_link = |c: &NodeId| tree[*c].link_status != LinkStatus::Normal;
+                is_link(a)
+                    .cmp(&is_link(b))
+                    .then_with(|| natural_cmp(name(a), name(b)))
+            }),
+        }
+    }
+}
+
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_cmp_folded(a, b).then_with(|| a.cmp(b))
+}
+
+fn natural_cmp_folded(mut a: &str, mut b: &str) -> Ordering {
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x_digits, x_rest) = split_digits(a);
+                let (y_digits, y_rest) = split_digits(b);
+                let x_trimmed = x_digits.trim_start_matches('0');
+                let y_trimmed = y_digits.trim_start_matches('0');
+                let ord = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                (a, b) = (x_rest, y_rest);
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+            }
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        names.sort_by(|a, b| natural_cmp(a, b));
+        names
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("v2", "v10"), Ordering::Less);
+        assert_eq!(
+            sorted(&["file10", "file9", "file1"]),
+            ["file1", "file9", "file10"]
+        );
+        assert_eq!(sorted(&["1.10", "1.9", "1.2"]), ["1.2", "1.9", "1.10"]);
+    }
+
+    #[test]
+    fn leading_zeros_break_ties() {
+        assert_eq!(natural_cmp("a01", "a1"), Ordering::Greater);
+        assert_eq!(natural_cmp("a001", "a01"), Ordering::Greater);
+        assert_eq!(natural_cmp("a002", "a1"), Ordering::Greater);
+        assert_eq!(natural_cmp("a00", "a0"), Ordering::Greater);
+    }
+
+    #[test]
+    fn ignores_case_then_falls_back_to_bytes() {
+        assert_eq!(
+            sorted(&["cherry", "Banana", "apple"]),
+            ["apple", "Banana", "cherry"]
+        );
+        assert_eq!(natural_cmp("A", "a"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a"), Ordering::Equal);
+    }
+}
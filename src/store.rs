@@ -0,0 +1,29 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+pub fn lock_file(file: &Path) -> io::Result<File> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(file.with_extension("lock"))
+}
+
+pub fn read(file: &Path) -> io::Result<Vec<u8>> {
+    match fs::read(file) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        result => result,
+    }
+}
+
+pub fn write_atomic(file: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = file.with_extension(format!("{}.tmp", std::process::id()));
+    let mut out = File::create(&tmp)?;
+    out.write_all(data)?;
+    out.sync_all()?;
+    fs::rename(&tmp, file)
+}
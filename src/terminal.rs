@@ -0,0 +1,203 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::panic;
+use std::str::FromStr;
+use std::sync::Once;
+use std::sync::atomic::{AtomicI32, AtomicU16, Ordering};
+
+use crossterm::{
+    cursor, execute,
+    terminal::{
+        self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+        enable_raw_mode,
+    },
+};
+use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend, layout::Rect};
+use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGQUIT, SIGTERM, SIGTSTP};
+use signal_hook::iterator::Signals;
+use signal_hook::low_level;
+
+pub type Tui = Terminal<CrosstermBackend<File>>;
+
+const MIN_HEIGHT: u16 = 5;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Height {
+    Lines(u16),
+    Percent(u16),
+}
+
+impl Height {
+    fn lines(self, rows: u16) -> u16 {
+        let lines = match self {
+            Height::Lines(n) => n,
+            Height::Percent(p) => (rows as u32 * p as u32 / 100) as u16,
+        };
+        lines.max(MIN_HEIGHT)
+    }
+}
+
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, percent) = match s.strip_suffix('%') {
+            Some(number) => (number, true),
+            None => (s, false),
+        };
+        match (number.parse::<u16>(), percent) {
+            (Ok(p), true) if (1..=100).contains(&p) => Ok(Height::Percent(p)),
+            (Ok(n), false) if n > 0 => Ok(Height::Lines(n)),
+            _ => Err(format!(
+                "expected a number of lines or a percentage, got {:?}",
+                s
+            )),
+        }
+    }
+}
+
+pub enum Signal {
+    Terminate,
+    Suspend,
+    Continue,
+}
+
+static PANIC_HOOK: Once = Once::new();
+// Lines of the inline viewport (0 when fullscreen) and the row it starts at.
+static INLINE_HEIGHT: AtomicU16 = AtomicU16::new(0);
+static INLINE_TOP: AtomicU16 = AtomicU16::new(0);
+// The real stdout while it points at the terminal, -1 otherwise.
+static SAVED_STDOUT: AtomicI32 = AtomicI32::new(-1);
+
+pub fn init(height: Option<Height>) -> io::Result<Tui> {
+    PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            previous(info);
+        }));
+    });
+    let rows = terminal::size()?.1;
+    match height.map(|h| h.lines(rows)).filter(|&lines| lines < rows) {
+        Some(lines) => {
+            INLINE_HEIGHT.store(lines, Ordering::Relaxed);
+            enter(false)?;
+            inline_terminal(lines)
+        }
+        None => {
+            enter(true)?;
+            Terminal::new(CrosstermBackend::new(tty()?))
+        }
+    }
+}
+
+pub fn restore() {
+    let _ = disable_raw_mode();
+    if let Ok(mut tty) = tty() {
+        let _ = if INLINE_HEIGHT.load(Ordering::Relaxed) > 0 {
+            let top = INLINE_TOP.load(Ordering::Relaxed);
+            execute!(
+                tty,
+                cursor::MoveTo(0, top),
+                Clear(ClearType::FromCursorDown),
+                cursor::Show
+            )
+        } else {
+            execute!(tty, LeaveAlternateScreen, cursor::Show)
+        };
+    }
+    release_stdout();
+}
+
+pub fn track(area: Rect) {
+    INLINE_TOP.store(area.y, Ordering::Relaxed);
+}
+
+pub fn suspend(terminal: &mut Tui) -> io::Result<()> {
+    restore();
+    low_level::emulate_default_handler(SIGTSTP)?;
+    match INLINE_HEIGHT.load(Ordering::Relaxed) {
+        0 => resume(terminal),
+        lines => {
+            enter(false)?;
+            *terminal = inline_terminal(lines)?;
+            Ok(())
+        }
+    }
+}
+
+pub fn resume(terminal: &mut Tui) -> io::Result<()> {
+    enter(INLINE_HEIGHT.load(Ordering::Relaxed) == 0)?;
+    terminal.clear()
+}
+
+fn tty() -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).open("/dev/tty")
+}
+
+fn enter(alternate_screen: bool) -> io::Result<()> {
+    capture_stdout()?;
+    enable_raw_mode()?;
+    if alternate_screen {
+        execute!(tty()?, EnterAlternateScreen)?;
+    }
+    Ok(())
+}
+
+fn inline_terminal(lines: u16) -> io::Result<Tui> {
+    let viewport = Viewport::Inline(lines);
+    let mut terminal =
+        Terminal::with_options(CrosstermBackend::new(tty()?), TerminalOptions { viewport })?;
+    track(terminal.get_frame().area());
+    Ok(terminal)
+}
+
+// crossterm writes its queries (e.g. the cursor position) to stdout, so point
+// it at the terminal while the UI is up and keep the result stream clean.
+fn capture_stdout() -> io::Result<()> {
+    if SAVED_STDOUT.load(Ordering::Relaxed) >= 0 {
+        return Ok(());
+    }
+    io::stdout().flush()?;
+    let tty = tty()?;
+    let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if saved < 0 || unsafe { libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    SAVED_STDOUT.store(saved, Ordering::Relaxed);
+    Ok(())
+}
+
+fn release_stdout() {
+    let saved = SAVED_STDOUT.swap(-1, Ordering::Relaxed);
+    if saved >= 0 {
+        let _ = io::stdout().flush();
+        unsafe {
+            libc::dup2(saved, libc::STDOUT_FILENO);
+            libc::close(saved);
+        }
+    }
+}
+
+pub struct SignalWatch {
+    signals: Signals,
+}
+
+impl SignalWatch {
+    pub fn new() -> io::Result<Self> {
+        let signals = Signals::new([SIGTERM, SIGHUP, SIGINT, SIGQUIT, SIGTSTP, SIGCONT])?;
+        Ok(Self { signals })
+    }
+
+    pub fn pending(&mut self) -> Vec<Signal> {
+        self.signals
+            .pending()
+            .map(|signal| match signal {
+                SIGTSTP => Signal::Suspend,
+                SIGCONT => Signal::Continue,
+                _ => Signal::Terminate,
+            })
+            .collect()
+    }
+}
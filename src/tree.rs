@@ -1,15 +1,63 @@
-use std::fs;
-use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, FileType};
+use std::io;
+use std::ops::{Index, IndexMut};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::sort::Sorter;
+use crate::visibility::Visibility;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(u64);
 
 #[derive(Debug)]
 pub struct DirEntry {
     pub name: String,
     pub path: PathBuf,
     pub expanded: bool,
-    pub children: Vec<DirEntry>,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
     pub is_loaded: bool,
     pub link_status: LinkStatus,
     pub link_target: Option<PathBuf>,
+    pub kind: NodeKind,
+    pub error: Option<LoadError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Dir,
+    Virtual,
+    Placeholder,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    PermissionDenied,
+    NotFound,
+    Io(String),
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::PermissionDenied => LoadError::PermissionDenied,
+            io::ErrorKind::NotFound => LoadError::NotFound,
+            _ => LoadError::Io(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::PermissionDenied => f.write_str("permission denied"),
+            LoadError::NotFound => f.write_str("no longer exists"),
+            LoadError::Io(message) => write!(f, "I/O error: {}", message),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -17,15 +65,11 @@ pub enum LinkStatus {
     Normal,
     SymlinkOk,
     SymlinkBroken,
+    SymlinkLoop,
 }
 
 impl DirEntry {
     pub fn new(path: PathBuf) -> Self {
-        let name = path
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| "/".to_string());
-
         let (link_status, link_target) = match fs::symlink_metadata(&path) {
             Ok(meta) => {
                 if meta.file_type().is_symlink() {
@@ -48,52 +92,563 @@ impl DirEntry {
             Err(_) => (LinkStatus::Normal, None),
         };
 
+        let mut entry = DirEntry::bare(path);
+        entry.link_status = link_status;
+        entry.link_target = link_target;
+        entry
+    }
+
+    fn bare(path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "/".to_string());
         Self {
             name,
             path,
             expanded: false,
+            parent: None,
             children: vec![],
             is_loaded: false,
-            link_status,
-            link_target,
+            link_status: LinkStatus::Normal,
+            link_target: None,
+            kind: NodeKind::Dir,
+            error: None,
+        }
+    }
+
+    pub fn listed(path: PathBuf, file_type: FileType) -> Self {
+        let mut entry = DirEntry::bare(path);
+        if file_type.is_symlink() {
+            entry.link_status = LinkStatus::SymlinkOk;
+            entry.link_target = entry.path.read_link().ok();
+        }
+        entry
+    }
+
+    pub fn new_virtual(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            path: PathBuf::new(),
+            expanded: false,
+            parent: None,
+            children: vec![],
+            is_loaded: true,
+            link_status: LinkStatus::Normal,
+            link_target: None,
+            kind: NodeKind::Virtual,
+            error: None,
+        }
+    }
+
+    pub fn new_placeholder(text: &str) -> Self {
+        let mut entry = DirEntry::new_virtual(text);
+        entry.kind = NodeKind::Placeholder;
+        entry
+    }
+
+    pub fn new_link(name: &str, target: PathBuf) -> Self {
+        let mut entry = DirEntry::new(target.clone());
+        entry.name = name.to_string();
+        entry.link_status = if target.is_dir() {
+            LinkStatus::SymlinkOk
+        } else {
+            LinkStatus::SymlinkBroken
+        };
+        entry.link_target = Some(target);
+        entry
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.kind != NodeKind::Dir
+    }
+}
+
+#[derive(Debug)]
+pub struct Tree {
+    nodes: HashMap<NodeId, DirEntry>,
+    next_id: u64,
+    root: NodeId,
+}
+
+impl Tree {
+    pub fn new() -> Self {
+        let root = NodeId(0);
+        Self {
+            nodes: HashMap::from([(root, DirEntry::new_virtual(""))]),
+            next_id: 1,
+            root,
+        }
+    }
+
+    pub fn from_paths(paths: Vec<PathBuf>, stat: bool, sorter: &Sorter) -> Self {
+        let mut tree = Tree::new();
+        let mut ids = HashMap::new();
+        for path in paths {
+            let mut parent = tree.root;
+            let mut current = PathBuf::new();
+            for component in path.components() {
+                if component == Component::CurDir {
+                    continue;
+                }
+                current.push(component);
+                if let Some(&id) = ids.get(&current) {
+                    parent = id;
+                    continue;
+                }
+                let mut entry = if stat {
+                    DirEntry::new(current.clone())
+                } else {
+                    DirEntry::bare(current.clone())
+                };
+                if stat && fs::symlink_metadata(&current).is_err() {
+                    entry.error = Some(LoadError::NotFound);
+                }
+                entry.name = component.as_os_str().to_string_lossy().to_string();
+                entry.is_loaded = true;
+                parent = tree.add(parent, entry);
+                ids.insert(current.clone(), parent);
+            }
+        }
+        let ids: Vec<NodeId> = tree.nodes.keys().copied().collect();
+        for id in ids {
+            tree.sort_children(id, sorter);
         }
+        tree
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(Some(id).filter(|id| self.contains(*id)), |id| {
+            self.nodes[id].parent
+        })
     }
 
-    pub fn load_children(&mut self) {
-        if self.is_loaded {
+    pub fn add(&mut self, parent: NodeId, entry: DirEntry) -> NodeId {
+        let id = self.alloc(parent, entry);
+        self[parent].children.push(id);
+        id
+    }
+
+    pub fn set_children(&mut self, parent: NodeId, entries: Vec<DirEntry>) {
+        for child in std::mem::take(&mut self[parent].children) {
+            self.remove(child);
+        }
+        for entry in entries {
+            self.add(parent, entry);
+        }
+    }
+
+    fn alloc(&mut self, parent: NodeId, mut entry: DirEntry) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        entry.parent = Some(parent);
+        self.nodes.insert(id, entry);
+        id
+    }
+
+    fn remove(&mut self, id: NodeId) {
+        if let Some(entry) = self.nodes.remove(&id) {
+            for child in entry.children {
+                self.remove(child);
+            }
+        }
+    }
+
+    pub fn load_children(&mut self, id: NodeId, visibility: &Visibility, sorter: &Sorter) {
+        if self[id].is_loaded {
             return;
         }
-        self.children = match fs::read_dir(&self.path) {
-            Ok(entries) => entries
-                .filter_map(|e| e.ok())
-                .filter(|e| e.path().is_dir())
-                .map(|e| DirEntry::new(e.path()))
-                .collect(),
-            Err(_) => vec![],
+        let subdirs = match visibility.subdirs(&self[id].path) {
+            Ok(subdirs) => subdirs,
+            Err(e) => return self.fail_load(id, e.into()),
         };
-        self.children.sort_by_key(|c| c.name.clone());
-        self.is_loaded = true;
+        let mut previous = HashMap::new();
+        for child in std::mem::take(&mut self[id].children) {
+            if self[child].kind == NodeKind::Dir {
+                previous.insert(self[child].path.clone(), child);
+            } else {
+                self.remove(child);
+            }
+        }
+        let mut children = Vec::with_capacity(subdirs.len());
+        for (path, file_type) in subdirs {
+            children.push(match previous.remove(&path) {
+                Some(existing) => existing,
+                None => self.alloc(id, DirEntry::listed(path, file_type)),
+            });
+        }
+        for stale in previous.into_values() {
+            self.remove(stale);
+        }
+        self.mark_loops(id, &children);
+        self[id].children = children;
+        self[id].is_loaded = true;
+        self[id].error = None;
+        self.sort_children(id, sorter);
+    }
+
+    pub fn child_paths(&self, id: NodeId) -> HashSet<PathBuf> {
+        self[id]
+            .children
+            .iter()
+            .map(|&c| self[c].path.clone())
+            .collect()
     }
 
-    pub fn load_only(&mut self, target: &Path) -> Option<usize> {
-        self.children = match fs::read_dir(&self.path) {
+    pub fn append_children(&mut self, id: NodeId, entries: Vec<DirEntry>) {
+        let placeholder = self[id]
+            .children
+            .last()
+            .copied()
+            .filter(|&c| self[c].kind == NodeKind::Placeholder);
+        if placeholder.is_some() {
+            self[id].children.pop();
+        }
+        let added: Vec<NodeId> = entries
+            .into_iter()
+            .map(|entry| self.add(id, entry))
+            .collect();
+        self.mark_loops(id, &added);
+        self[id].children.extend(placeholder);
+    }
+
+    pub fn finish_load(&mut self, id: NodeId, seen: &HashSet<PathBuf>, sorter: &Sorter) {
+        let (keep, stale): (Vec<NodeId>, Vec<NodeId>) = std::mem::take(&mut self[id].children)
+            .into_iter()
+            .partition(|&c| self[c].kind == NodeKind::Dir && seen.contains(&self[c].path));
+        for child in stale {
+            self.remove(child);
+        }
+        self[id].children = keep;
+        self[id].is_loaded = true;
+        self[id].error = None;
+        self.sort_children(id, sorter);
+    }
+
+    pub fn fail_load(&mut self, id: NodeId, error: LoadError) {
+        for child in std::mem::take(&mut self[id].children) {
+            self.remove(child);
+        }
+        self[id].is_loaded = true;
+        self[id].error = Some(error);
+    }
+
+    fn mark_loops(&mut self, parent: NodeId, children: &[NodeId]) {
+        let links: Vec<NodeId> = children
+            .iter()
+            .copied()
+            .filter(|&c| self[c].link_status == LinkStatus::SymlinkOk)
+            .collect();
+        if links.is_empty() {
+            return;
+        }
+        let ancestors: HashSet<(u64, u64)> = self
+            .ancestors(parent)
+            .filter(|&a| !self[a].is_virtual())
+            .filter_map(|a| file_id(&self[a].path))
+            .collect();
+        for link in links {
+            if file_id(&self[link].path).is_some_and(|id| ancestors.contains(&id)) {
+                self[link].link_status = LinkStatus::SymlinkLoop;
+            }
+        }
+    }
+
+    pub fn set_placeholder(&mut self, id: NodeId, text: &str) {
+        let existing = self[id]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self[c].kind == NodeKind::Placeholder);
+        match existing {
+            Some(placeholder) => self[placeholder].name = text.to_string(),
+            None => {
+                self.add(id, DirEntry::new_placeholder(text));
+            }
+        }
+    }
+
+    pub fn stop_loading(&mut self, id: NodeId, message: &str, sorter: &Sorter) {
+        self.set_placeholder(id, message);
+        self.sort_children(id, sorter);
+    }
+
+    fn sort_children(&mut self, id: NodeId, sorter: &Sorter) {
+        let mut children = std::mem::take(&mut self[id].children);
+        sorter.sort(self, &mut children);
+        children.sort_by_key(|&c| self[c].kind == NodeKind::Placeholder);
+        self[id].children = children;
+    }
+
+    pub fn loaded_in(&self, id: NodeId) -> Vec<NodeId> {
+        let mut loaded = vec![];
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = &self[id];
+            if node.is_loaded && !node.is_virtual() {
+                loaded.push(id);
+            }
+            stack.extend(&node.children);
+        }
+        loaded
+    }
+
+    pub fn loaded_at(&self, path: &Path) -> Vec<NodeId> {
+        self.nodes
+            .iter()
+            .filter(|(_, n)| n.is_loaded && !n.is_virtual() && n.path == path)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    pub fn contains_path(&self, path: &Path) -> bool {
+        self.nodes
+            .values()
+            .any(|n| n.kind == NodeKind::Dir && n.path == path)
+    }
+
+    pub fn expanded_dirs(&self) -> HashSet<PathBuf> {
+        self.nodes
+            .values()
+            .filter(|n| n.expanded && n.is_loaded && !n.is_virtual())
+            .map(|n| n.path.clone())
+            .collect()
+    }
+
+    pub fn resort(&mut self, id: NodeId, sorter: &Sorter) {
+        if !self[id].is_virtual() {
+            self.sort_children(id, sorter);
+        }
+        for child in self[id].children.clone() {
+            self.resort(child, sorter);
+        }
+    }
+
+    pub fn load_only(&mut self, id: NodeId, target: &Path) -> Option<NodeId> {
+        let entries = match fs::read_dir(&self[id].path) {
             Ok(entries) => entries
                 .filter_map(|e| e.ok())
-                .filter(|e| e.path().is_dir())
-                .map(|e| DirEntry::new(e.path()))
-                .filter(|e| target.starts_with(&e.path))
+                .filter(|e| target.starts_with(e.path()))
+                .filter_map(|e| {
+                    let file_type = e.file_type().ok()?;
+                    let path = e.path();
+                    let is_dir = file_type.is_dir() || (file_type.is_symlink() && path.is_dir());
+                    is_dir.then(|| DirEntry::listed(path, file_type))
+                })
                 .collect(),
-            Err(_) => vec![],
+            Err(_) => {
+                let dir = &self[id].path;
+                match target
+                    .strip_prefix(dir)
+                    .map(|rest| rest.components().next())
+                {
+                    Ok(Some(first)) if target.is_dir() => vec![DirEntry::new(dir.join(first))],
+                    _ => vec![],
+                }
+            }
         };
-        self.children.sort_by_key(|c| c.name.clone());
-        self.is_loaded = false;
-        self.children.first().map(|_| 0)
+        self.set_children(id, entries);
+        self[id].is_loaded = false;
+        self[id].children.first().copied()
+    }
+
+    pub fn collapse_all(&mut self, id: NodeId) {
+        self[id].expanded = false;
+        for child in self[id].children.clone() {
+            self.collapse_all(child);
+        }
+    }
+}
+
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+impl Index<NodeId> for Tree {
+    type Output = DirEntry;
+
+    fn index(&self, id: NodeId) -> &DirEntry {
+        &self.nodes[&id]
+    }
+}
+
+impl IndexMut<NodeId> for Tree {
+    fn index_mut(&mut self, id: NodeId) -> &mut DirEntry {
+        self.nodes.get_mut(&id).expect("node id is not in the tree")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sort::SortMode;
+
+    fn sorter() -> Sorter {
+        Sorter::new(SortMode::Natural, &[])
+    }
+
+    fn names(tree: &Tree, id: NodeId) -> Vec<&str> {
+        tree[id]
+            .children
+            .iter()
+            .map(|&c| tree[c].name.as_str())
+            .collect()
     }
 
-    pub fn collapse_all(&mut self) {
-        self.expanded = false;
-        for child in self.children.iter_mut() {
-            child.collapse_all();
+    fn child(tree: &Tree, id: NodeId, name: &str) -> NodeId {
+        tree[id]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| tree[c].name == name)
+            .unwrap()
+    }
+
+    fn scratch(name: &str, dirs: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cda-tree-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for sub in dirs {
+            fs::create_dir_all(dir.join(sub)).unwrap();
         }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn set_children_frees_whole_subtrees() {
+        let mut tree = Tree::new();
+        let root = tree.root();
+        let a = tree.add(root, DirEntry::bare(PathBuf::from("/a")));
+        let b = tree.add(a, DirEntry::bare(PathBuf::from("/a/b")));
+        let c = tree.add(b, DirEntry::bare(PathBuf::from("/a/b/c")));
+
+        tree.set_children(root, vec![DirEntry::bare(PathBuf::from("/d"))]);
+        assert!(!tree.contains(a) && !tree.contains(b) && !tree.contains(c));
+        assert_eq!(tree.nodes.len(), 2);
+        assert_eq!(names(&tree, root), ["d"]);
+    }
+
+    #[test]
+    fn load_children_keeps_ids_by_path() {
+        let dir = scratch("reload", &["x", "y"]);
+        let mut tree = Tree::new();
+        let root = tree.root();
+        let id = tree.add(root, DirEntry::new(dir.clone()));
+        let visibility = Visibility::new(false, false, &[]).unwrap();
+
+        tree.load_children(id, &visibility, &sorter());
+        assert_eq!(names(&tree, id), ["x", "y"]);
+        let x = child(&tree, id, "x");
+        let y = child(&tree, id, "y");
+        tree.add(x, DirEntry::bare(dir.join("x/inner")));
+
+        fs::remove_dir(dir.join("y")).unwrap();
+        fs::create_dir(dir.join("z")).unwrap();
+        tree[id].is_loaded = false;
+        tree.load_children(id, &visibility, &sorter());
+        assert_eq!(names(&tree, id), ["x", "z"]);
+        assert_eq!(child(&tree, id, "x"), x);
+        assert_eq!(tree[x].children.len(), 1);
+        assert!(!tree.contains(y));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn finish_load_drops_unseen_children_and_the_placeholder() {
+        let mut tree = Tree::new();
+        let root = tree.root();
+        let a = tree.add(root, DirEntry::bare(PathBuf::from("/a")));
+        let kept = tree.add(a, DirEntry::bare(PathBuf::from("/a/kept")));
+        let gone = tree.add(a, DirEntry::bare(PathBuf::from("/a/gone")));
+        tree.set_placeholder(a, "loading…");
+
+        let seen = HashSet::from([PathBuf::from("/a/kept")]);
+        tree.finish_load(a, &seen, &sorter());
+        assert_eq!(tree[a].children, [kept]);
+        assert!(!tree.contains(gone));
+        assert!(tree[a].is_loaded);
+    }
+
+    #[test]
+    fn append_children_keeps_the_placeholder_last() {
+        let mut tree = Tree::new();
+        let root = tree.root();
+        let a = tree.add(root, DirEntry::bare(PathBuf::from("/a")));
+        tree.set_placeholder(a, "loading…");
+        tree.append_children(a, vec![DirEntry::bare(PathBuf::from("/a/x"))]);
+        tree.append_children(a, vec![DirEntry::bare(PathBuf::from("/a/y"))]);
+        tree.set_placeholder(a, "timed out");
+
+        assert_eq!(names(&tree, a), ["x", "y", "timed out"]);
+        let last = *tree[a].children.last().unwrap();
+        assert_eq!(tree[last].kind, NodeKind::Placeholder);
+    }
+
+    #[test]
+    fn ancestors_walk_up_to_the_root() {
+        let mut tree = Tree::new();
+        let root = tree.root();
+        let a = tree.add(root, DirEntry::bare(PathBuf::from("/a")));
+        let b = tree.add(a, DirEntry::bare(PathBuf::from("/a/b")));
+
+        assert_eq!(tree.ancestors(b).collect::<Vec<_>>(), [b, a, root]);
+        tree.set_children(root, vec![]);
+        assert_eq!(tree.ancestors(b).count(), 0);
+    }
+
+    fn from_paths(paths: &[&str]) -> Tree {
+        Tree::from_paths(paths.iter().map(PathBuf::from).collect(), false, &sorter())
+    }
+
+    #[test]
+    fn from_paths_shares_prefixes() {
+        let tree = from_paths(&["a/b", "a/c", "a/b/d", "a/b"]);
+        let root = tree.root();
+        assert_eq!(names(&tree, root), ["a"]);
+        let a = child(&tree, root, "a");
+        assert_eq!(names(&tree, a), ["b", "c"]);
+        let b = child(&tree, a, "b");
+        assert_eq!(names(&tree, b), ["d"]);
+        assert_eq!(tree[child(&tree, b, "d")].path, Path::new("a/b/d"));
+        assert_eq!(tree.nodes.len(), 5);
+    }
+
+    #[test]
+    fn from_paths_skips_current_dir_components() {
+        let tree = from_paths(&["./x/y", "x/./z", "."]);
+        let root = tree.root();
+        assert_eq!(names(&tree, root), ["x"]);
+        let x = child(&tree, root, "x");
+        assert_eq!(names(&tree, x), ["y", "z"]);
+        assert_eq!(tree[x].path, Path::new("x"));
+    }
+
+    #[test]
+    fn from_paths_keeps_absolute_and_relative_roots_apart() {
+        let tree = from_paths(&["/usr/bin", "usr/lib"]);
+        let root = tree.root();
+        assert_eq!(names(&tree, root), ["/", "usr"]);
+        let abs = child(&tree, child(&tree, root, "/"), "usr");
+        assert_eq!(tree[abs].path, Path::new("/usr"));
+        assert_eq!(names(&tree, abs), ["bin"]);
+        assert_eq!(names(&tree, child(&tree, root, "usr")), ["lib"]);
+    }
+
+    #[test]
+    fn from_paths_sorts_every_level() {
+        let tree = from_paths(&["v10/b", "v2", "a", "v10/a10", "v10/a9"]);
+        let root = tree.root();
+        assert_eq!(names(&tree, root), ["a", "v2", "v10"]);
+        assert_eq!(names(&tree, child(&tree, root, "v10")), ["a9", "a10", "b"]);
+        assert!(tree.nodes.values().all(|n| n.is_loaded));
     }
 }
@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::fs::{self, FileType};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use ignore::overrides::{Override, OverrideBuilder};
+
+#[derive(Debug, Clone)]
+pub struct Visibility {
+    pub show_hidden: bool,
+    pub show_ignored: bool,
+    gitignore: bool,
+    ignore: Override,
+    pinned: Vec<PathBuf>,
+}
+
+impl Visibility {
+    pub fn new(show_hidden: bool, gitignore: bool, globs: &[String]) -> Result<Self, String> {
+        let mut builder = OverrideBuilder::new("/");
+        for glob in globs {
+            builder
+                .add(&format!("!{}", glob))
+                .map_err(|e| format!("invalid ignore glob {:?}: {}", glob, e))?;
+        }
+        let ignore = builder.build().map_err(|e| e.to_string())?;
+        Ok(Self {
+            show_hidden,
+            show_ignored: false,
+            gitignore,
+            ignore,
+            pinned: vec![],
+        })
+    }
+
+    pub fn pin(&mut self, path: &Path) {
+        if !self.pinned.iter().any(|p| p == path) {
+            self.pinned.push(path.to_path_buf());
+        }
+    }
+
+    pub fn walker(&self, dir: &Path) -> WalkBuilder {
+        let gitignore = self.gitignore && !self.show_ignored;
+        let ignore = if self.show_ignored {
+            Override::empty()
+        } else {
+            self.ignore.clone()
+        };
+        let mut builder = WalkBuilder::new(dir);
+        builder
+            .hidden(!self.show_hidden)
+            .parents(gitignore)
+            .ignore(gitignore)
+            .git_ignore(gitignore)
+            .git_global(gitignore)
+            .git_exclude(gitignore)
+            .overrides(ignore)
+            .follow_links(false);
+        builder
+    }
+
+    pub fn subdirs(&self, dir: &Path) -> io::Result<Vec<(PathBuf, FileType)>> {
+        let mut dirs = vec![];
+        self.for_each_subdir(dir, |path, file_type| {
+            dirs.push((path, file_type));
+            true
+        })?;
+        Ok(dirs)
+    }
+
+    pub fn for_each_subdir(
+        &self,
+        dir: &Path,
+        mut f: impl FnMut(PathBuf, FileType) -> bool,
+    ) -> io::Result<()> {
+        let mut listed = HashSet::new();
+        for entry in self.walker(dir).max_depth(Some(1)).build() {
+            let entry = match entry {
+                Ok(entry) if entry.depth() == 1 => entry,
+                Err(e) if e.depth() == Some(0) && !e.is_partial() => {
+                    let message = e.to_string();
+                    return Err(e
+                        .into_io_error()
+                        .unwrap_or_else(|| io::Error::other(message)));
+                }
+                _ => continue,
+            };
+            let Some(file_type) = entry.file_type() else {
+                continue;
+            };
+            if !is_dir(entry.path(), file_type) {
+                continue;
+            }
+            let path = entry.into_path();
+            listed.insert(path.clone());
+            if !f(path, file_type) {
+                return Ok(());
+            }
+        }
+
+        for pinned in &self.pinned {
+            if let Ok(rest) = pinned.strip_prefix(dir)
+                && let Some(first) = rest.components().next()
+            {
+                let child = dir.join(first);
+                if listed.contains(&child) {
+                    continue;
+                }
+                let Ok(meta) = fs::symlink_metadata(&child) else {
+                    continue;
+                };
+                if is_dir(&child, meta.file_type()) {
+                    listed.insert(child.clone());
+                    if !f(child, meta.file_type()) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_dir(path: &Path, file_type: FileType) -> bool {
+    file_type.is_dir() || (file_type.is_symlink() && path.is_dir())
+}
@@ -0,0 +1,92 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::event::{CreateKind, ModifyKind, RemoveKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+pub struct Watcher {
+    watcher: RecommendedWatcher,
+    rx: Receiver<notify::Result<Event>>,
+    watched: HashSet<PathBuf>,
+    dirty: HashSet<PathBuf>,
+    reported: HashMap<PathBuf, Instant>,
+}
+
+const THROTTLE: Duration = Duration::from_millis(500);
+
+impl Watcher {
+    pub fn new() -> notify::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })?;
+        Ok(Self {
+            watcher,
+            rx,
+            watched: HashSet::new(),
+            dirty: HashSet::new(),
+            reported: HashMap::new(),
+        })
+    }
+
+    pub fn sync(&mut self, dirs: HashSet<PathBuf>) {
+        for dir in self.watched.difference(&dirs) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&self.watched) {
+            let _ = self.watcher.watch(dir, RecursiveMode::NonRecursive);
+        }
+        self.dirty.retain(|dir| dirs.contains(dir));
+        self.reported.retain(|dir, _| dirs.contains(dir));
+        self.watched = dirs;
+    }
+
+    pub fn changed(&mut self, is_known: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+        for event in self.rx.try_iter().filter_map(|e| e.ok()) {
+            let paths: Vec<&PathBuf> = match event.kind {
+                EventKind::Create(CreateKind::File) | EventKind::Remove(RemoveKind::File) => {
+                    continue;
+                }
+                EventKind::Create(CreateKind::Folder)
+                | EventKind::Remove(RemoveKind::Folder)
+                | EventKind::Any => event.paths.iter().collect(),
+                EventKind::Create(_)
+                | EventKind::Remove(_)
+                | EventKind::Modify(ModifyKind::Name(_)) => event
+                    .paths
+                    .iter()
+                    .filter(|path| path.is_dir() || is_known(path))
+                    .collect(),
+                _ => continue,
+            };
+            for path in paths {
+                if let Some(parent) = path.parent() {
+                    self.dirty.insert(parent.to_path_buf());
+                }
+            }
+        }
+
+        let due: Vec<PathBuf> = self
+            .dirty
+            .iter()
+            .filter(|dir| {
+                self.reported
+                    .get(*dir)
+                    .is_none_or(|at| at.elapsed() >= THROTTLE)
+            })
+            .cloned()
+            .collect();
+        let now = Instant::now();
+        for dir in &due {
+            self.dirty.remove(dir);
+            self.reported.insert(dir.clone(), now);
+        }
+        due
+    }
+
+    pub fn defer(&mut self, dir: PathBuf) {
+        self.dirty.insert(dir);
+    }
+}